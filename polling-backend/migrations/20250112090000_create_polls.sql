@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS polls (
+    id CHAR(36) NOT NULL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    creator_id VARCHAR(255) NOT NULL,
+    created_at DATETIME(6) NOT NULL,
+    is_closed BOOLEAN NOT NULL DEFAULT FALSE,
+    INDEX idx_polls_creator (creator_id),
+    INDEX idx_polls_created_at (created_at)
+);
+
+CREATE TABLE IF NOT EXISTS poll_options (
+    id CHAR(36) NOT NULL PRIMARY KEY,
+    poll_id CHAR(36) NOT NULL,
+    position INT NOT NULL,
+    text VARCHAR(255) NOT NULL,
+    CONSTRAINT fk_poll_options_poll FOREIGN KEY (poll_id) REFERENCES polls (id) ON DELETE CASCADE,
+    UNIQUE KEY uq_poll_options_position (poll_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS votes (
+    id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    poll_id CHAR(36) NOT NULL,
+    option_id CHAR(36) NOT NULL,
+    created_at DATETIME(6) NOT NULL,
+    CONSTRAINT fk_votes_poll FOREIGN KEY (poll_id) REFERENCES polls (id) ON DELETE CASCADE,
+    CONSTRAINT fk_votes_option FOREIGN KEY (option_id) REFERENCES poll_options (id) ON DELETE CASCADE,
+    INDEX idx_votes_poll (poll_id)
+);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS users (
+    id CHAR(36) NOT NULL PRIMARY KEY,
+    username VARCHAR(255) NOT NULL,
+    created_at DATETIME(6) NOT NULL,
+    UNIQUE KEY uq_users_username (username)
+);
+
+CREATE TABLE IF NOT EXISTS credentials (
+    id VARBINARY(1023) NOT NULL PRIMARY KEY,
+    user_id CHAR(36) NOT NULL,
+    passkey JSON NOT NULL,
+    counter INT UNSIGNED NOT NULL DEFAULT 0,
+    created_at DATETIME(6) NOT NULL,
+    last_used_at DATETIME(6) NULL,
+    CONSTRAINT fk_credentials_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    INDEX idx_credentials_user (user_id)
+);
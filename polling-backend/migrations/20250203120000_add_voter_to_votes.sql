@@ -0,0 +1,8 @@
+ALTER TABLE polls
+    ADD COLUMN allow_vote_change BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Votes cast before ballots were attributed keep a NULL voter, which the
+-- unique key ignores.
+ALTER TABLE votes
+    ADD COLUMN voter_id CHAR(36) NULL,
+    ADD UNIQUE KEY uq_votes_voter (poll_id, voter_id);
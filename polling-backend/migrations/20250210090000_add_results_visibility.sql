@@ -0,0 +1,2 @@
+ALTER TABLE polls
+    ADD COLUMN results_visibility VARCHAR(16) NOT NULL DEFAULT 'always';
@@ -0,0 +1,3 @@
+ALTER TABLE polls
+    ADD COLUMN deleted_at DATETIME(6) NULL,
+    ADD INDEX idx_polls_deleted_at (deleted_at);
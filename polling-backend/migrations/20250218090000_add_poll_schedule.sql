@@ -0,0 +1,4 @@
+ALTER TABLE polls
+    ADD COLUMN opens_at DATETIME(6) NULL,
+    ADD COLUMN closes_at DATETIME(6) NULL,
+    ADD INDEX idx_polls_closes_at (closes_at);
@@ -0,0 +1,9 @@
+ALTER TABLE polls
+    ADD COLUMN min_choices INT UNSIGNED NOT NULL DEFAULT 1,
+    ADD COLUMN max_choices INT UNSIGNED NOT NULL DEFAULT 1;
+
+-- A ballot is now one row per selected option, so a voter may appear more
+-- than once per poll but never twice for the same option.
+ALTER TABLE votes
+    DROP INDEX uq_votes_voter,
+    ADD UNIQUE KEY uq_votes_voter_option (poll_id, voter_id, option_id);
@@ -0,0 +1,7 @@
+ALTER TABLE polls
+    ADD COLUMN voting_method VARCHAR(16) NOT NULL DEFAULT 'plurality';
+
+-- 0 is the first preference. Unranked ballots store every selection at 0,
+-- so counting preference 0 gives plurality tallies and first preferences alike.
+ALTER TABLE votes
+    ADD COLUMN preference INT NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+ALTER TABLE polls
+    ADD COLUMN max_score INT UNSIGNED NOT NULL DEFAULT 5;
+
+-- Points given to the option on score polls; NULL for every other method.
+ALTER TABLE votes
+    ADD COLUMN score INT UNSIGNED NULL;
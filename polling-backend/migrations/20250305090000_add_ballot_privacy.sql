@@ -0,0 +1,16 @@
+ALTER TABLE polls
+    ADD COLUMN ballot_privacy VARCHAR(16) NOT NULL DEFAULT 'attributed';
+
+-- Groups a ballot's rows without needing the voter, so anonymous ballots
+-- can be recounted. Older rows keep NULL and are grouped by voter instead.
+ALTER TABLE votes
+    ADD COLUMN ballot_id CHAR(36) NULL;
+
+-- Who voted on an anonymous poll. Deliberately has no timestamp or other
+-- ordering that could tie a row back to its ballot in `votes`.
+CREATE TABLE IF NOT EXISTS poll_voters (
+    poll_id CHAR(36) NOT NULL,
+    voter_id CHAR(36) NOT NULL,
+    PRIMARY KEY (poll_id, voter_id),
+    CONSTRAINT fk_poll_voters_poll FOREIGN KEY (poll_id) REFERENCES polls (id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- `is_closed` stays in sync with `state` for existing readers.
+ALTER TABLE polls
+    ADD COLUMN state VARCHAR(16) NOT NULL DEFAULT 'open',
+    ADD COLUMN closed_at DATETIME(6) NULL,
+    ADD COLUMN closed_by VARCHAR(255) NULL,
+    ADD INDEX idx_polls_state (state);
+
+UPDATE polls SET state = 'closed' WHERE is_closed = TRUE;
+UPDATE polls SET state = 'scheduled'
+    WHERE is_closed = FALSE AND opens_at > UTC_TIMESTAMP(6);
@@ -0,0 +1,12 @@
+ALTER TABLE polls
+    ADD COLUMN visibility VARCHAR(16) NOT NULL DEFAULT 'public',
+    ADD COLUMN share_token CHAR(64) NULL,
+    ADD UNIQUE INDEX uq_polls_share_token (share_token);
+
+-- Usernames allowed to open a private poll without its share token.
+CREATE TABLE IF NOT EXISTS poll_members (
+    poll_id CHAR(36) NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    PRIMARY KEY (poll_id, username),
+    CONSTRAINT fk_poll_members_poll FOREIGN KEY (poll_id) REFERENCES polls (id) ON DELETE CASCADE
+);
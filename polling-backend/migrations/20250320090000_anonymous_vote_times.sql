@@ -0,0 +1,10 @@
+-- Rows of anonymous ballots carry no time, so they cannot be matched to
+-- when a voter was seen. Their ballot ids are random and say nothing about
+-- the order ballots arrived in.
+ALTER TABLE votes
+    MODIFY COLUMN created_at DATETIME(6) NULL;
+
+UPDATE votes
+    JOIN polls ON polls.id = votes.poll_id
+    SET votes.created_at = NULL
+    WHERE polls.ballot_privacy = 'anonymous';
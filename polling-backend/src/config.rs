@@ -1,3 +1,4 @@
+use chrono::Duration;
 use http::{header::CONTENT_TYPE, HeaderName, HeaderValue, Method};
 use tower_http::cors::CorsLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -12,30 +13,85 @@ pub fn setup_tracing() {
         .init();
 }
 
-pub fn setup_cors() -> CorsLayer {
-    let frontend_urls: Vec<String> = std::env::var("FRONTEND_URL")
+/// Origins of the frontend, from the comma-separated `FRONTEND_URL`.
+pub fn frontend_origins() -> Vec<HeaderValue> {
+    std::env::var("FRONTEND_URL")
         .unwrap_or_else(|_| "https://frontend.3.108.234.78.sslip.io".to_string())
         .split(',')
-        .map(|s| s.to_string())
-        .collect();
-
-    let origins = frontend_urls
-        .iter()
         .map(|url| url.parse::<HeaderValue>().unwrap())
-        .collect::<Vec<HeaderValue>>();
+        .collect()
+}
 
+pub fn setup_cors() -> CorsLayer {
     CorsLayer::new()
         .allow_credentials(true)
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
         .allow_headers([
             CONTENT_TYPE,
             HeaderName::from_static("accept"),
             HeaderName::from_static("authorization"),
             HeaderName::from_static("x-requested-with"),
         ])
-        .allow_origin(origins)
+        .allow_origin(frontend_origins())
         .expose_headers([
             HeaderName::from_static("access-control-allow-credentials"),
             HeaderName::from_static("access-control-allow-origin"),
         ])
 }
+
+/// How long a deleted poll can still be restored before it is purged.
+pub fn poll_undo_window() -> Duration {
+    let secs = std::env::var("POLL_UNDO_WINDOW_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(300);
+    Duration::seconds(secs)
+}
+
+/// Bounds every new poll must satisfy, whichever transport created it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PollLimits {
+    pub min_options: usize,
+    pub max_options: usize,
+    /// Limits are counted in characters after normalization.
+    pub max_title_len: usize,
+    pub max_option_len: usize,
+}
+
+impl Default for PollLimits {
+    fn default() -> Self {
+        Self {
+            min_options: 2,
+            max_options: 20,
+            max_title_len: 200,
+            max_option_len: 100,
+        }
+    }
+}
+
+impl PollLimits {
+    /// Defaults overridden by `POLL_MIN_OPTIONS`, `POLL_MAX_OPTIONS`,
+    /// `POLL_MAX_TITLE_LEN` and `POLL_MAX_OPTION_LEN`.
+    pub fn from_env() -> Self {
+        fn var(name: &str, default: usize) -> usize {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            min_options: var("POLL_MIN_OPTIONS", defaults.min_options),
+            max_options: var("POLL_MAX_OPTIONS", defaults.max_options),
+            max_title_len: var("POLL_MAX_TITLE_LEN", defaults.max_title_len),
+            max_option_len: var("POLL_MAX_OPTION_LEN", defaults.max_option_len),
+        }
+    }
+}
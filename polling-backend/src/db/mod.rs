@@ -0,0 +1,19 @@
+pub mod poll;
+pub mod user;
+
+use sqlx::migrate::Migrator;
+use sqlx::mysql::{MySqlPool, MySqlPoolOptions};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Connects to MySQL and brings the schema up to date.
+pub async fn connect(database_url: &str) -> Result<MySqlPool, sqlx::Error> {
+    let pool = MySqlPoolOptions::new()
+        .max_connections(10)
+        .connect(database_url)
+        .await?;
+
+    MIGRATOR.run(&pool).await?;
+
+    Ok(pool)
+}
@@ -0,0 +1,793 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::Transaction;
+use sqlx::{FromRow, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::listing::{PollFilter, PollSort};
+use crate::models::poll::{
+    BallotPrivacy, Poll, PollOption, PollState, PollVisibility, ResultsVisibility, VoteOutcome,
+    VotingMethod,
+};
+use crate::tally::Ballot;
+
+#[derive(FromRow)]
+struct PollRow {
+    id: String,
+    title: String,
+    creator_id: String,
+    created_at: DateTime<Utc>,
+    is_closed: bool,
+    allow_vote_change: bool,
+    results_visibility: String,
+    opens_at: Option<DateTime<Utc>>,
+    closes_at: Option<DateTime<Utc>>,
+    voting_method: String,
+    min_choices: u32,
+    max_choices: u32,
+    max_score: u32,
+    ballot_privacy: String,
+    state: String,
+    closed_at: Option<DateTime<Utc>>,
+    closed_by: Option<String>,
+    visibility: String,
+    share_token: Option<String>,
+}
+
+#[derive(FromRow)]
+struct OptionRow {
+    id: String,
+    poll_id: String,
+    text: String,
+    votes: i64,
+}
+
+const SELECT_POLLS: &str = "SELECT id, title, creator_id, created_at, is_closed, \
+     allow_vote_change, results_visibility, opens_at, closes_at, voting_method, min_choices, max_choices, \
+     max_score, ballot_privacy, state, closed_at, closed_by, visibility, share_token FROM polls";
+
+const SELECT_OPTIONS: &str = "SELECT o.id, o.poll_id, o.text, COUNT(v.id) AS votes \
+     FROM poll_options o \
+     LEFT JOIN votes v ON v.option_id = o.id AND v.preference = 0";
+
+const GROUP_OPTIONS: &str = "GROUP BY o.id, o.poll_id, o.text, o.position \
+     ORDER BY o.poll_id, o.position";
+
+fn assemble(row: PollRow, options: Vec<OptionRow>, allowed_users: Vec<String>) -> Poll {
+    let options: Vec<PollOption> = options
+        .into_iter()
+        .map(|opt| PollOption {
+            id: opt.id,
+            text: opt.text,
+            votes: opt.votes as i32,
+            percentage: 0.0,
+        })
+        .collect();
+
+    let mut poll = Poll {
+        id: row.id,
+        title: row.title,
+        creator_id: row.creator_id,
+        total_votes: 0,
+        options,
+        created_at: row.created_at,
+        is_closed: row.is_closed,
+        allow_vote_change: row.allow_vote_change,
+        results_visibility: ResultsVisibility::parse(&row.results_visibility).unwrap_or_default(),
+        opens_at: row.opens_at,
+        closes_at: row.closes_at,
+        voting_method: VotingMethod::parse(&row.voting_method).unwrap_or_default(),
+        min_choices: row.min_choices,
+        max_choices: row.max_choices,
+        max_score: row.max_score,
+        ballot_privacy: BallotPrivacy::parse(&row.ballot_privacy).unwrap_or_default(),
+        state: PollState::parse(&row.state).unwrap_or_default(),
+        closed_at: row.closed_at,
+        closed_by: row.closed_by,
+        visibility: PollVisibility::parse(&row.visibility).unwrap_or_default(),
+        share_token: row.share_token,
+        allowed_users,
+    };
+    poll.refresh_tally();
+    poll
+}
+
+pub async fn insert_poll(pool: &MySqlPool, poll: &Poll) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO polls \
+         (id, title, creator_id, created_at, is_closed, allow_vote_change, results_visibility, \
+          opens_at, closes_at, voting_method, min_choices, max_choices, max_score, \
+          ballot_privacy, state, closed_at, closed_by, visibility, share_token) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&poll.id)
+    .bind(&poll.title)
+    .bind(&poll.creator_id)
+    .bind(poll.created_at)
+    .bind(poll.is_closed)
+    .bind(poll.allow_vote_change)
+    .bind(poll.results_visibility.as_str())
+    .bind(poll.opens_at)
+    .bind(poll.closes_at)
+    .bind(poll.voting_method.as_str())
+    .bind(poll.min_choices)
+    .bind(poll.max_choices)
+    .bind(poll.max_score)
+    .bind(poll.ballot_privacy.as_str())
+    .bind(poll.state.as_str())
+    .bind(poll.closed_at)
+    .bind(&poll.closed_by)
+    .bind(poll.visibility.as_str())
+    .bind(&poll.share_token)
+    .execute(&mut *tx)
+    .await?;
+
+    insert_members(&mut tx, &poll.id, &poll.allowed_users).await?;
+
+    for (position, option) in poll.options.iter().enumerate() {
+        sqlx::query("INSERT INTO poll_options (id, poll_id, position, text) VALUES (?, ?, ?, ?)")
+            .bind(&option.id)
+            .bind(&poll.id)
+            .bind(position as i32)
+            .bind(&option.text)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+async fn insert_members(
+    tx: &mut Transaction<'_, MySql>,
+    poll_id: &str,
+    usernames: &[String],
+) -> Result<(), sqlx::Error> {
+    for username in usernames {
+        sqlx::query("INSERT INTO poll_members (poll_id, username) VALUES (?, ?)")
+            .bind(poll_id)
+            .bind(username)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Allow-listed usernames of each of `poll_ids`.
+async fn fetch_members(
+    pool: &MySqlPool,
+    poll_ids: &[&str],
+) -> Result<HashMap<String, Vec<String>>, sqlx::Error> {
+    let mut members: HashMap<String, Vec<String>> = HashMap::new();
+    if poll_ids.is_empty() {
+        return Ok(members);
+    }
+
+    let mut query =
+        QueryBuilder::<MySql>::new("SELECT poll_id, username FROM poll_members WHERE poll_id IN (");
+    let mut ids = query.separated(", ");
+    for poll_id in poll_ids {
+        ids.push_bind(*poll_id);
+    }
+    query.push(") ORDER BY poll_id, username");
+    let rows: Vec<(String, String)> = query.build_query_as().fetch_all(pool).await?;
+
+    for (poll_id, username) in rows {
+        members.entry(poll_id).or_default().push(username);
+    }
+    Ok(members)
+}
+
+pub async fn set_share_token(
+    pool: &MySqlPool,
+    poll_id: &str,
+    share_token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE polls SET share_token = ? WHERE id = ?")
+        .bind(share_token)
+        .bind(poll_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn update_poll(
+    pool: &MySqlPool,
+    poll: &Poll,
+    allow_votes: bool,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Taken before the vote check so no ballot can land mid-edit
+    let found: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM polls WHERE id = ? AND deleted_at IS NULL FOR UPDATE")
+            .bind(&poll.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    if found.is_none() {
+        return Ok(false);
+    }
+    if !allow_votes {
+        let (has_votes,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM votes WHERE poll_id = ?)")
+                .bind(&poll.id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if has_votes {
+            return Ok(false);
+        }
+    }
+
+    sqlx::query(
+        "UPDATE polls SET title = ?, max_choices = ?, visibility = ?, share_token = ? \
+         WHERE id = ?",
+    )
+    .bind(&poll.title)
+    .bind(poll.max_choices)
+    .bind(poll.visibility.as_str())
+    .bind(&poll.share_token)
+    .bind(&poll.id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM poll_members WHERE poll_id = ?")
+        .bind(&poll.id)
+        .execute(&mut *tx)
+        .await?;
+    insert_members(&mut tx, &poll.id, &poll.allowed_users).await?;
+
+    let mut removed = QueryBuilder::<MySql>::new("DELETE FROM poll_options WHERE poll_id = ");
+    removed.push_bind(&poll.id).push(" AND id NOT IN (");
+    let mut ids = removed.separated(", ");
+    for option in &poll.options {
+        ids.push_bind(&option.id);
+    }
+    removed.push(")");
+    removed.build().execute(&mut *tx).await?;
+
+    // Move kept options out of the way so reordering never trips the
+    // unique (poll_id, position) key
+    sqlx::query("UPDATE poll_options SET position = -1 - position WHERE poll_id = ?")
+        .bind(&poll.id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (position, option) in poll.options.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO poll_options (id, poll_id, position, text) VALUES (?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE position = VALUES(position), text = VALUES(text)",
+        )
+        .bind(&option.id)
+        .bind(&poll.id)
+        .bind(position as i32)
+        .bind(&option.text)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+pub async fn fetch_poll(pool: &MySqlPool, poll_id: &str) -> Result<Option<Poll>, sqlx::Error> {
+    fetch_one(pool, poll_id, "deleted_at IS NULL").await
+}
+
+pub async fn fetch_deleted_poll(
+    pool: &MySqlPool,
+    poll_id: &str,
+) -> Result<Option<(Poll, DateTime<Utc>)>, sqlx::Error> {
+    let deleted_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT deleted_at FROM polls WHERE id = ? AND deleted_at IS NOT NULL")
+            .bind(poll_id)
+            .fetch_optional(pool)
+            .await?;
+
+    let Some(deleted_at) = deleted_at else {
+        return Ok(None);
+    };
+
+    Ok(fetch_one(pool, poll_id, "deleted_at IS NOT NULL")
+        .await?
+        .map(|poll| (poll, deleted_at)))
+}
+
+async fn fetch_one(
+    pool: &MySqlPool,
+    poll_id: &str,
+    condition: &str,
+) -> Result<Option<Poll>, sqlx::Error> {
+    let row: Option<PollRow> =
+        sqlx::query_as(&format!("{SELECT_POLLS} WHERE id = ? AND {condition}"))
+            .bind(poll_id)
+            .fetch_optional(pool)
+            .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let options: Vec<OptionRow> = sqlx::query_as(&format!(
+        "{SELECT_OPTIONS} WHERE o.poll_id = ? {GROUP_OPTIONS}"
+    ))
+    .bind(poll_id)
+    .fetch_all(pool)
+    .await?;
+
+    let allowed_users = fetch_members(pool, &[poll_id])
+        .await?
+        .remove(poll_id)
+        .unwrap_or_default();
+
+    Ok(Some(assemble(row, options, allowed_users)))
+}
+
+/// Pushes the position of a poll in `sort` order for `viewer`, matching
+/// `PollSort::sort_key`.
+fn push_sort_key(query: &mut QueryBuilder<'_, MySql>, sort: PollSort, viewer: Option<&str>) {
+    match sort {
+        PollSort::Newest => {
+            query.push("-TIMESTAMPDIFF(MICROSECOND, '1970-01-01', created_at)");
+        }
+        // Matches `Poll::results_visible_to`
+        PollSort::MostVotes => {
+            query
+                .push("CASE WHEN is_closed OR results_visibility = 'always' OR creator_id = ")
+                .push_bind(viewer.map(str::to_string))
+                .push(
+                    " THEN -(SELECT COUNT(*) FROM votes v \
+                     WHERE v.poll_id = polls.id AND v.preference = 0) ELSE 0 END",
+                );
+        }
+        PollSort::ClosingSoon => {
+            query.push(
+                "CASE WHEN is_closed OR closes_at IS NULL THEN 9223372036854775807 \
+                 ELSE TIMESTAMPDIFF(MICROSECOND, '1970-01-01', closes_at) END",
+            );
+        }
+    }
+}
+
+pub async fn fetch_polls(pool: &MySqlPool, filter: &PollFilter) -> Result<Vec<Poll>, sqlx::Error> {
+    let mut query = QueryBuilder::<MySql>::new("SELECT * FROM (");
+    query.push(SELECT_POLLS.replacen(" FROM polls", ", ", 1));
+    push_sort_key(&mut query, filter.sort, filter.viewer.as_deref());
+    query.push(" AS sort_key FROM polls");
+    query.push(" WHERE deleted_at IS NULL");
+    // Matches `Poll::is_listed_for`
+    match &filter.viewer {
+        Some(viewer) => {
+            query
+                .push(" AND ((state <> 'draft' AND visibility = 'public') OR creator_id = ")
+                .push_bind(viewer)
+                .push(")");
+        }
+        None => {
+            query.push(" AND state <> 'draft' AND visibility = 'public'");
+        }
+    }
+    if let Some(status) = filter.status {
+        query.push(" AND state = ").push_bind(status.as_str());
+    }
+    if let Some(creator) = &filter.creator {
+        query.push(" AND creator_id = ").push_bind(creator);
+    }
+    if let Some(created_after) = filter.created_after {
+        query.push(" AND created_at > ").push_bind(created_after);
+    }
+    if let Some(search) = &filter.search {
+        let escaped = search
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query
+            .push(" AND title LIKE ")
+            .push_bind(format!("%{escaped}%"));
+    }
+    query.push(") AS listed");
+    if let Some(after) = &filter.after {
+        query
+            .push(" WHERE (sort_key, id) > (")
+            .push_bind(after.key)
+            .push(", ")
+            .push_bind(&after.id)
+            .push(")");
+    }
+    query
+        .push(" ORDER BY sort_key, id LIMIT ")
+        .push_bind(filter.limit as u64);
+
+    let rows: Vec<PollRow> = query.build_query_as().fetch_all(pool).await?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut options_query = QueryBuilder::<MySql>::new(SELECT_OPTIONS);
+    options_query.push(" WHERE o.poll_id IN (");
+    let mut ids = options_query.separated(", ");
+    for row in &rows {
+        ids.push_bind(&row.id);
+    }
+    options_query.push(") ").push(GROUP_OPTIONS);
+    let options: Vec<OptionRow> = options_query.build_query_as().fetch_all(pool).await?;
+
+    let mut grouped: HashMap<String, Vec<OptionRow>> = HashMap::new();
+    for opt in options {
+        grouped.entry(opt.poll_id.clone()).or_default().push(opt);
+    }
+    let poll_ids: Vec<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+    let mut members = fetch_members(pool, &poll_ids).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let options = grouped.remove(&row.id).unwrap_or_default();
+            let allowed_users = members.remove(&row.id).unwrap_or_default();
+            assemble(row, options, allowed_users)
+        })
+        .collect())
+}
+
+pub async fn fetch_voted_polls(
+    pool: &MySqlPool,
+    voter_id: Uuid,
+) -> Result<Vec<(Poll, Option<Ballot>)>, sqlx::Error> {
+    let poll_ids: Vec<(String,)> = sqlx::query_as(
+        "SELECT p.id FROM polls p \
+         WHERE p.deleted_at IS NULL AND ( \
+             EXISTS (SELECT 1 FROM votes v WHERE v.poll_id = p.id AND v.voter_id = ?) \
+             OR EXISTS (SELECT 1 FROM poll_voters pv WHERE pv.poll_id = p.id AND pv.voter_id = ?)) \
+         ORDER BY p.created_at DESC",
+    )
+    .bind(voter_id.to_string())
+    .bind(voter_id.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    let mut voted = Vec::with_capacity(poll_ids.len());
+    for (poll_id,) in poll_ids {
+        let Some(poll) = fetch_poll(pool, &poll_id).await? else {
+            continue;
+        };
+        let ballot = if poll.ballot_privacy == BallotPrivacy::Anonymous {
+            None
+        } else {
+            let rows: Vec<VoteMark> = sqlx::query_as(
+                "SELECT option_id, score FROM votes WHERE poll_id = ? AND voter_id = ? \
+                 ORDER BY preference, id",
+            )
+            .bind(&poll_id)
+            .bind(voter_id.to_string())
+            .fetch_all(pool)
+            .await?;
+            Some(ballot_from_rows(rows))
+        };
+        voted.push((poll, ballot));
+    }
+
+    Ok(voted)
+}
+
+pub async fn cast_ballot(
+    pool: &MySqlPool,
+    poll_id: &str,
+    voter_id: Uuid,
+    ballot: &Ballot,
+    allow_change: bool,
+) -> Result<VoteOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Serialize ballots per poll so a voter's rows are read and replaced atomically
+    let settings: Option<(String, String)> = sqlx::query_as(
+        "SELECT voting_method, ballot_privacy FROM polls \
+         WHERE id = ? AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(poll_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((voting_method, ballot_privacy)) = settings else {
+        return Ok(VoteOutcome::PollNotFound);
+    };
+    let ranked = VotingMethod::parse(&voting_method).is_some_and(|method| method.is_ranked());
+
+    if BallotPrivacy::parse(&ballot_privacy) == Some(BallotPrivacy::Anonymous) {
+        let inserted =
+            sqlx::query("INSERT IGNORE INTO poll_voters (poll_id, voter_id) VALUES (?, ?)")
+                .bind(poll_id)
+                .bind(voter_id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        if inserted.rows_affected() == 0 {
+            return Ok(VoteOutcome::AlreadyVoted);
+        }
+
+        insert_ballot(&mut tx, poll_id, None, ballot, ranked).await?;
+        tx.commit().await?;
+        return Ok(VoteOutcome::Recorded);
+    }
+
+    let rows: Vec<VoteMark> = sqlx::query_as(
+        "SELECT option_id, score FROM votes WHERE poll_id = ? AND voter_id = ? \
+         ORDER BY preference, id",
+    )
+    .bind(poll_id)
+    .bind(voter_id.to_string())
+    .fetch_all(&mut *tx)
+    .await?;
+    let previous = ballot_from_rows(rows);
+
+    let outcome = if previous.option_ids.is_empty() {
+        VoteOutcome::Recorded
+    } else if previous == *ballot {
+        VoteOutcome::Unchanged
+    } else if allow_change {
+        sqlx::query("DELETE FROM votes WHERE poll_id = ? AND voter_id = ?")
+            .bind(poll_id)
+            .bind(voter_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        VoteOutcome::Changed {
+            previous_option_ids: previous.option_ids,
+        }
+    } else {
+        VoteOutcome::AlreadyVoted
+    };
+
+    if matches!(outcome, VoteOutcome::Recorded | VoteOutcome::Changed { .. }) {
+        insert_ballot(&mut tx, poll_id, Some(voter_id), ballot, ranked).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(outcome)
+}
+
+/// Writes one row per selected option, all sharing a fresh random ballot
+/// id. Anonymous ballots (no `voter_id`) are stored without a timestamp.
+async fn insert_ballot(
+    tx: &mut Transaction<'_, MySql>,
+    poll_id: &str,
+    voter_id: Option<Uuid>,
+    ballot: &Ballot,
+    ranked: bool,
+) -> Result<(), sqlx::Error> {
+    let ballot_id = Uuid::new_v4().to_string();
+    let created_at = voter_id.map(|_| Utc::now());
+    for (preference, option_id) in ballot.option_ids.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO votes \
+             (poll_id, option_id, voter_id, ballot_id, preference, score, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(poll_id)
+        .bind(option_id)
+        .bind(voter_id.map(|id| id.to_string()))
+        .bind(&ballot_id)
+        .bind(if ranked { preference as i32 } else { 0 })
+        .bind(ballot.scores.get(option_id))
+        .bind(created_at)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// An `(option_id, score)` row of a ballot.
+type VoteMark = (String, Option<u32>);
+
+/// Builds a ballot from rows already in preference order.
+fn ballot_from_rows(rows: Vec<VoteMark>) -> Ballot {
+    let mut ballot = Ballot::default();
+    for (option_id, score) in rows {
+        if let Some(score) = score {
+            ballot.scores.insert(option_id.clone(), score);
+        }
+        ballot.option_ids.push(option_id);
+    }
+    ballot
+}
+
+/// Every ballot on a poll. Votes from before ballots were attributed have
+/// neither a ballot nor a voter and count as single-option ballots.
+pub async fn fetch_ballots(pool: &MySqlPool, poll_id: &str) -> Result<Vec<Ballot>, sqlx::Error> {
+    let rows: Vec<(String, String, Option<u32>)> = sqlx::query_as(
+        "SELECT COALESCE(ballot_id, voter_id, CAST(id AS CHAR)) AS ballot, option_id, score \
+         FROM votes WHERE poll_id = ? ORDER BY ballot, preference, id",
+    )
+    .bind(poll_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut ballots: Vec<Ballot> = Vec::new();
+    let mut current: Option<String> = None;
+    let mut marks = Vec::new();
+    for (ballot, option_id, score) in rows {
+        if current.as_ref().is_some_and(|current| *current != ballot) {
+            ballots.push(ballot_from_rows(std::mem::take(&mut marks)));
+        }
+        current = Some(ballot);
+        marks.push((option_id, score));
+    }
+    if !marks.is_empty() {
+        ballots.push(ballot_from_rows(marks));
+    }
+
+    Ok(ballots)
+}
+
+/// Ballots that carry their voter, i.e. those on attributed polls.
+pub async fn fetch_attributed_ballots(
+    pool: &MySqlPool,
+    poll_id: &str,
+) -> Result<Vec<(Uuid, Ballot)>, sqlx::Error> {
+    let rows: Vec<(String, String, Option<u32>)> = sqlx::query_as(
+        "SELECT voter_id, option_id, score FROM votes \
+         WHERE poll_id = ? AND voter_id IS NOT NULL ORDER BY voter_id, preference, id",
+    )
+    .bind(poll_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut grouped: Vec<(String, Vec<VoteMark>)> = Vec::new();
+    for (voter_id, option_id, score) in rows {
+        match grouped.last_mut() {
+            Some((current, marks)) if *current == voter_id => marks.push((option_id, score)),
+            _ => grouped.push((voter_id, vec![(option_id, score)])),
+        }
+    }
+
+    Ok(grouped
+        .into_iter()
+        .filter_map(|(voter_id, marks)| {
+            Some((Uuid::parse_str(&voter_id).ok()?, ballot_from_rows(marks)))
+        })
+        .collect())
+}
+
+/// Saves `poll`'s lifecycle fields if it is still in state `from`.
+pub async fn set_state(
+    pool: &MySqlPool,
+    poll: &Poll,
+    from: PollState,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE polls SET state = ?, is_closed = ?, closed_at = ?, closed_by = ?, closes_at = ? \
+         WHERE id = ? AND state = ? AND deleted_at IS NULL",
+    )
+    .bind(poll.state.as_str())
+    .bind(poll.is_closed)
+    .bind(poll.closed_at)
+    .bind(&poll.closed_by)
+    .bind(poll.closes_at)
+    .bind(&poll.id)
+    .bind(from.as_str())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Opens every scheduled poll whose `opens_at` has passed, returning their ids.
+pub async fn open_scheduled(
+    pool: &MySqlPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT id FROM polls \
+         WHERE state = 'scheduled' AND opens_at <= ? AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for poll_id in &ids {
+        sqlx::query("UPDATE polls SET state = 'open' WHERE id = ?")
+            .bind(poll_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(ids)
+}
+
+/// Closes every open poll whose `closes_at` has passed, returning their ids.
+pub async fn close_expired(
+    pool: &MySqlPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT id FROM polls \
+         WHERE state IN ('scheduled', 'open') AND closes_at <= ? AND deleted_at IS NULL \
+         FOR UPDATE",
+    )
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for poll_id in &ids {
+        sqlx::query(
+            "UPDATE polls SET state = 'closed', is_closed = TRUE, closed_at = closes_at, \
+             closed_by = NULL WHERE id = ?",
+        )
+        .bind(poll_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(ids)
+}
+
+pub async fn delete_votes(pool: &MySqlPool, poll_id: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM votes WHERE poll_id = ?")
+        .bind(poll_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM poll_voters WHERE poll_id = ?")
+        .bind(poll_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+pub async fn soft_delete_poll(
+    pool: &MySqlPool,
+    poll_id: &str,
+    deleted_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE polls SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+        .bind(deleted_at)
+        .bind(poll_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn restore_poll(pool: &MySqlPool, poll_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE polls SET deleted_at = NULL WHERE id = ?")
+        .bind(poll_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Hard-deletes polls soft-deleted before `deleted_before`; options and votes cascade.
+pub async fn purge_deleted(
+    pool: &MySqlPool,
+    deleted_before: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let ids: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM polls WHERE deleted_at < ? FOR UPDATE")
+            .bind(deleted_before)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    sqlx::query("DELETE FROM polls WHERE deleted_at < ?")
+        .bind(deleted_before)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(ids)
+}
@@ -0,0 +1,102 @@
+use chrono::Utc;
+use sqlx::mysql::MySqlPool;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+fn decode_passkey(json: &str) -> Result<Passkey, sqlx::Error> {
+    serde_json::from_str(json).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+fn encode_passkey(passkey: &Passkey) -> Result<String, sqlx::Error> {
+    serde_json::to_string(passkey).map_err(|e| sqlx::Error::Encode(Box::new(e)))
+}
+
+pub async fn find_user_id(pool: &MySqlPool, username: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    let id: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?;
+
+    id.map(|id| Uuid::parse_str(&id).map_err(|e| sqlx::Error::Decode(Box::new(e))))
+        .transpose()
+}
+
+pub async fn find_username(pool: &MySqlPool, user_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+        .bind(user_id.to_string())
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn fetch_passkeys(pool: &MySqlPool, user_id: Uuid) -> Result<Vec<Passkey>, sqlx::Error> {
+    let rows: Vec<String> = sqlx::query_scalar(
+        "SELECT CAST(passkey AS CHAR) FROM credentials WHERE user_id = ? ORDER BY created_at",
+    )
+    .bind(user_id.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(|json| decode_passkey(json)).collect()
+}
+
+/// Stores a freshly registered passkey, creating the user on their first one.
+/// Returns false, storing nothing, when `username` belongs to another user
+/// (e.g. two registrations raced for it).
+pub async fn insert_credential(
+    pool: &MySqlPool,
+    user_id: Uuid,
+    username: &str,
+    passkey: &Passkey,
+) -> Result<bool, sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("INSERT IGNORE INTO users (id, username, created_at) VALUES (?, ?, ?)")
+        .bind(user_id.to_string())
+        .bind(username)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    let owner: String = sqlx::query_scalar("SELECT id FROM users WHERE username = ? FOR UPDATE")
+        .bind(username)
+        .fetch_one(&mut *tx)
+        .await?;
+    if owner != user_id.to_string() {
+        return Ok(false);
+    }
+
+    sqlx::query("INSERT INTO credentials (id, user_id, passkey, created_at) VALUES (?, ?, ?, ?)")
+        .bind(passkey.cred_id().as_ref())
+        .bind(user_id.to_string())
+        .bind(encode_passkey(passkey)?)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// The signature counter, which `Passkey` only exposes in its serialized form.
+fn passkey_counter(json: &str) -> Result<u32, sqlx::Error> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    Ok(value["cred"]["counter"]
+        .as_u64()
+        .and_then(|counter| u32::try_from(counter).ok())
+        .unwrap_or_default())
+}
+
+/// Persists a passkey after `update_credential` bumped its counter or backup state.
+pub async fn update_credential(pool: &MySqlPool, passkey: &Passkey) -> Result<(), sqlx::Error> {
+    let json = encode_passkey(passkey)?;
+    sqlx::query("UPDATE credentials SET passkey = ?, counter = ?, last_used_at = ? WHERE id = ?")
+        .bind(&json)
+        .bind(passkey_counter(&json)?)
+        .bind(Utc::now())
+        .bind(passkey.cred_id().as_ref())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -1,52 +1,213 @@
 use axum::{
+    extract::rejection::{JsonRejection, QueryRejection},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use thiserror::Error;
 
+use crate::models::poll::PollState;
+use crate::store::StoreError;
+
+/// A single invalid request field, reported inside `ApiError::Validation`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Every error the HTTP API can return.
+///
+/// Responses carry the human readable `error` message, a stable
+/// machine-readable `code` and optional `details`, e.g.
+/// `{"error": "Poll not found", "code": "poll_not_found", "details": {"poll_id": "..."}}`.
 #[derive(Error, Debug)]
-pub enum WebauthnError {
-    #[error("unknown webauthn error")]
-    Unknown,
-    #[error("Corrupt Session")]
+pub enum ApiError {
+    // Authentication and authorization
+    #[error("Not authenticated")]
+    NotAuthenticated,
+    #[error("Not authorized")]
+    Forbidden,
+    #[error("Corrupt session")]
     CorruptSession,
-    #[error("User Not Found")]
-    UserNotFound,
-    #[error("User Has No Credentials")]
+    #[error("Session error: {0}")]
+    Session(#[from] tower_sessions::session::Error),
+    #[error("User {0} not found")]
+    UserNotFound(String),
+    #[error("Username {0} is already taken")]
+    UsernameTaken(String),
+    #[error("User has no credentials")]
     UserHasNoCredentials,
-    #[error("Deserialising Session failed: {0}")]
-    InvalidSessionState(#[from] tower_sessions::session::Error),
-    #[error("Session Error: {0}")]
-    SessionError(StatusCode, String),
-    #[error("Not Authenticated")]
-    NotAuthenticated,
-    #[error("Unauthorized")]
-    Unauthorized,
+    #[error("Passkey registration failed")]
+    RegistrationFailed,
+    #[error("Passkey authentication failed")]
+    AuthenticationFailed,
+
+    // Validation
+    #[error("Invalid request")]
+    Validation(Vec<FieldError>),
+
+    // Not found
+    #[error("Poll not found")]
+    PollNotFound(String),
+    #[error("Option not found")]
+    OptionNotFound(String),
+
+    // Conflicts with the current poll state
+    #[error("Poll is closed")]
+    PollClosed(String),
+    #[error("Poll is not open yet")]
+    PollNotOpen(String),
+    #[error("Already voted in this poll")]
+    AlreadyVoted(String),
+    #[error("Poll cannot go from {} to {}", .from.as_str(), .to.as_str())]
+    InvalidTransition {
+        poll_id: String,
+        from: PollState,
+        to: PollState,
+    },
+    #[error("Poll already has votes; options can only be appended")]
+    PollHasVotes(String),
+    #[error("Results are hidden until the poll closes")]
+    ResultsHidden(String),
+    #[error("Ballots on this poll are anonymous")]
+    AnonymousBallots(String),
+
+    // Server side failures
+    #[error("Storage error: {0}")]
+    Storage(#[from] StoreError),
+    #[error("Internal error: {0}")]
+    Internal(String),
 }
-impl IntoResponse for WebauthnError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
-            WebauthnError::Unknown => (StatusCode::INTERNAL_SERVER_ERROR, "Unknown error occurred"),
-            WebauthnError::NotAuthenticated => (StatusCode::UNAUTHORIZED, "Not authenticated"),
-            WebauthnError::Unauthorized => (StatusCode::FORBIDDEN, "Not authorized"),
-            WebauthnError::CorruptSession => (StatusCode::INTERNAL_SERVER_ERROR, "Corrupt Session"),
-            WebauthnError::UserNotFound => (StatusCode::INTERNAL_SERVER_ERROR, "User Not Found"),
-            WebauthnError::UserHasNoCredentials => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "User Has No Credentials")
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotAuthenticated | ApiError::AuthenticationFailed => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden | ApiError::ResultsHidden(_) | ApiError::AnonymousBallots(_) => {
+                StatusCode::FORBIDDEN
+            }
+            ApiError::CorruptSession | ApiError::RegistrationFailed => StatusCode::BAD_REQUEST,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::UserNotFound(_)
+            | ApiError::UserHasNoCredentials
+            | ApiError::PollNotFound(_)
+            | ApiError::OptionNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::PollClosed(_)
+            | ApiError::PollNotOpen(_)
+            | ApiError::AlreadyVoted(_)
+            | ApiError::UsernameTaken(_)
+            | ApiError::PollHasVotes(_)
+            | ApiError::InvalidTransition { .. } => StatusCode::CONFLICT,
+            ApiError::Session(_) | ApiError::Storage(_) | ApiError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Stable identifier clients can branch on; never changes with the wording.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotAuthenticated => "not_authenticated",
+            ApiError::Forbidden => "forbidden",
+            ApiError::CorruptSession => "corrupt_session",
+            ApiError::Session(_) => "session_error",
+            ApiError::UserNotFound(_) => "user_not_found",
+            ApiError::UsernameTaken(_) => "username_taken",
+            ApiError::UserHasNoCredentials => "user_has_no_credentials",
+            ApiError::RegistrationFailed => "registration_failed",
+            ApiError::AuthenticationFailed => "authentication_failed",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::PollNotFound(_) => "poll_not_found",
+            ApiError::OptionNotFound(_) => "option_not_found",
+            ApiError::PollClosed(_) => "poll_closed",
+            ApiError::PollNotOpen(_) => "poll_not_open",
+            ApiError::AlreadyVoted(_) => "already_voted",
+            ApiError::PollHasVotes(_) => "poll_has_votes",
+            ApiError::InvalidTransition { .. } => "invalid_transition",
+            ApiError::ResultsHidden(_) => "results_hidden",
+            ApiError::AnonymousBallots(_) => "anonymous_ballots",
+            ApiError::Storage(_) => "storage_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            ApiError::UserNotFound(username) | ApiError::UsernameTaken(username) => {
+                Some(json!({ "username": username }))
             }
-            WebauthnError::InvalidSessionState(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Deserialising Session failed",
-            ),
-            WebauthnError::SessionError(_, _msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Session Error")
+            ApiError::Validation(fields) => Some(json!({ "fields": fields })),
+            ApiError::PollNotFound(poll_id)
+            | ApiError::PollClosed(poll_id)
+            | ApiError::PollNotOpen(poll_id)
+            | ApiError::AlreadyVoted(poll_id)
+            | ApiError::PollHasVotes(poll_id)
+            | ApiError::ResultsHidden(poll_id)
+            | ApiError::AnonymousBallots(poll_id) => Some(json!({ "poll_id": poll_id })),
+            ApiError::OptionNotFound(option_id) => Some(json!({ "option_id": option_id })),
+            ApiError::InvalidTransition { poll_id, from, to } => {
+                Some(json!({ "poll_id": poll_id, "from": from, "to": to }))
             }
-        };
+            _ => None,
+        }
+    }
+
+    /// Message safe to show clients; server side failures stay vague.
+    pub fn public_message(&self) -> String {
+        match self {
+            ApiError::Session(_) => "Session error".to_string(),
+            ApiError::Storage(_) => "Storage error".to_string(),
+            ApiError::Internal(_) => "Internal error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::Validation(vec![FieldError::new(
+            "body",
+            "invalid",
+            rejection.body_text(),
+        )])
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::Validation(vec![FieldError::new(
+            "query",
+            "invalid",
+            rejection.body_text(),
+        )])
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("request failed -> {:?}", self);
+        }
 
         let body = Json(json!({
-            "error": message
+            "error": self.public_message(),
+            "code": self.code(),
+            "details": self.details(),
         }));
 
         (status, body).into_response()
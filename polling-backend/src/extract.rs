@@ -0,0 +1,17 @@
+//! Request extractors that reject with `ApiError`, so malformed bodies and
+//! query strings get the same error shape as every other failure.
+
+use axum::extract::FromRequest;
+use axum::extract::FromRequestParts;
+
+use crate::error::ApiError;
+
+/// `axum::Json`, rejecting unreadable bodies as `validation_failed`.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+/// `axum::extract::Query`, rejecting bad query strings as `validation_failed`.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub struct ApiQuery<T>(pub T);
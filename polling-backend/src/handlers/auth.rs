@@ -7,35 +7,31 @@ use axum::{
 use tower_sessions::Session;
 use uuid::Uuid;
 use webauthn_rs::prelude::{
-    PasskeyAuthentication, PublicKeyCredential, RegisterPublicKeyCredential,
+    PasskeyAuthentication, PasskeyRegistration, PublicKeyCredential, RegisterPublicKeyCredential,
 };
 
-use crate::error::WebauthnError;
+use crate::error::ApiError;
+use crate::extract::ApiJson;
 use crate::state::AppState;
+use crate::store::StoreError;
 
 pub async fn start_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Path(username): Path<String>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("Start register");
-    let user_unique_id = {
-        let users_guard = app_state.users.lock().await;
-        users_guard
-            .name_to_id
-            .get(&username)
-            .copied()
-            .unwrap_or_else(Uuid::new_v4)
-    };
+    let user_unique_id = app_state
+        .users
+        .find_user_id(&username)
+        .await?
+        .unwrap_or_else(Uuid::new_v4);
 
     let _ = session.remove_value("reg_state").await;
 
     let exclude_credentials = {
-        let users_guard = app_state.users.lock().await;
-        users_guard
-            .keys
-            .get(&user_unique_id)
-            .map(|keys| keys.iter().map(|sk| sk.cred_id().clone()).collect())
+        let keys = app_state.users.passkeys(user_unique_id).await?;
+        (!keys.is_empty()).then(|| keys.iter().map(|sk| sk.cred_id().clone()).collect())
     };
 
     let res = match app_state.webauthn.start_passkey_registration(
@@ -56,7 +52,7 @@ pub async fn start_register(
         }
         Err(e) => {
             tracing::info!("challenge_register -> {:?}", e);
-            return Err(WebauthnError::Unknown);
+            return Err(ApiError::Internal(e.to_string()));
         }
     };
 
@@ -68,15 +64,16 @@ pub async fn start_register(
 pub async fn finish_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
-    Json(reg): Json<RegisterPublicKeyCredential>,
-) -> Result<impl IntoResponse, WebauthnError> {
-    let (username, user_unique_id, reg_state) = match session.get("reg_state").await? {
-        Some((username, user_unique_id, reg_state)) => (username, user_unique_id, reg_state),
-        None => {
-            tracing::info!("Failed to get session");
-            return Err(WebauthnError::CorruptSession);
-        }
-    };
+    ApiJson(reg): ApiJson<RegisterPublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (username, user_unique_id, reg_state): (String, Uuid, PasskeyRegistration) =
+        match session.get("reg_state").await? {
+            Some((username, user_unique_id, reg_state)) => (username, user_unique_id, reg_state),
+            None => {
+                tracing::info!("Failed to get session");
+                return Err(ApiError::CorruptSession);
+            }
+        };
 
     let _ = session.remove_value("reg_state").await;
 
@@ -85,22 +82,20 @@ pub async fn finish_register(
         .finish_passkey_registration(&reg, &reg_state)
     {
         Ok(sk) => {
-            let mut users_guard = app_state.users.lock().await;
-
-            // TODO This is where we would store the credential in a db, or persist them in some other way.
-            users_guard
-                .keys
-                .entry(user_unique_id)
-                .and_modify(|keys| keys.push(sk.clone()))
-                .or_insert_with(|| vec![sk.clone()]);
-
-            users_guard.name_to_id.insert(username, user_unique_id);
+            app_state
+                .users
+                .add_passkey(user_unique_id, &username, &sk)
+                .await
+                .map_err(|e| match e {
+                    StoreError::UsernameTaken(username) => ApiError::UsernameTaken(username),
+                    e => e.into(),
+                })?;
 
             StatusCode::OK
         }
         Err(e) => {
             tracing::info!("challenge_register -> {:?}", e);
-            StatusCode::BAD_REQUEST
+            return Err(ApiError::RegistrationFailed);
         }
     };
 
@@ -111,32 +106,27 @@ pub async fn start_authentication(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Path(username): Path<String>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("Start Authentication");
     // Remove any before stuff
     let _ = session.remove_value("auth_state").await;
 
-    let users_guard = app_state.users.lock().await;
-
-    let user_unique_id = users_guard
-        .name_to_id
-        .get(&username)
-        .copied()
-        .ok_or(WebauthnError::UserNotFound)?;
+    let user_unique_id = app_state
+        .users
+        .find_user_id(&username)
+        .await?
+        .ok_or_else(|| ApiError::UserNotFound(username.clone()))?;
 
-    let allow_credentials = users_guard
-        .keys
-        .get(&user_unique_id)
-        .ok_or(WebauthnError::UserHasNoCredentials)?;
+    let allow_credentials = app_state.users.passkeys(user_unique_id).await?;
+    if allow_credentials.is_empty() {
+        return Err(ApiError::UserHasNoCredentials);
+    }
 
     let res = match app_state
         .webauthn
-        .start_passkey_authentication(allow_credentials)
+        .start_passkey_authentication(&allow_credentials)
     {
         Ok((rcr, auth_state)) => {
-            // Drop the mutex to allow the mut borrows below to proceed
-            drop(users_guard);
-
             // sessions are safer than cookies
             session
                 .insert("auth_state", (user_unique_id, auth_state))
@@ -146,7 +136,7 @@ pub async fn start_authentication(
         }
         Err(e) => {
             tracing::info!("challenge_authenticate -> {:?}", e);
-            return Err(WebauthnError::Unknown);
+            return Err(ApiError::Internal(e.to_string()));
         }
     };
     Ok(res)
@@ -155,12 +145,12 @@ pub async fn start_authentication(
 pub async fn finish_authentication(
     Extension(app_state): Extension<AppState>,
     session: Session,
-    Json(auth): Json<PublicKeyCredential>,
-) -> Result<impl IntoResponse, WebauthnError> {
+    ApiJson(auth): ApiJson<PublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
     let (user_unique_id, auth_state): (Uuid, PasskeyAuthentication) = session
         .get("auth_state")
         .await?
-        .ok_or(WebauthnError::CorruptSession)?;
+        .ok_or(ApiError::CorruptSession)?;
 
     let _ = session.remove_value("auth_state").await;
 
@@ -169,25 +159,24 @@ pub async fn finish_authentication(
         .finish_passkey_authentication(&auth, &auth_state)
     {
         Ok(auth_result) => {
-            let mut users_guard = app_state.users.lock().await;
-
             // Get username from user_id
-            let username = users_guard
-                .name_to_id
-                .iter()
-                .find(|(_, &id)| id == user_unique_id)
-                .map(|(name, _)| name.clone())
-                .ok_or(WebauthnError::UserNotFound)?;
-
-            users_guard
-                .keys
-                .get_mut(&user_unique_id)
-                .map(|keys| {
-                    keys.iter_mut().for_each(|k| {
-                        k.update_credential(&auth_result);
-                    })
-                })
-                .ok_or(WebauthnError::UserHasNoCredentials)?;
+            let username = app_state
+                .users
+                .find_username(user_unique_id)
+                .await?
+                .ok_or_else(|| ApiError::UserNotFound(user_unique_id.to_string()))?;
+
+            let mut keys = app_state.users.passkeys(user_unique_id).await?;
+            if keys.is_empty() {
+                return Err(ApiError::UserHasNoCredentials);
+            }
+
+            // Persist the bumped counter so cloned authenticators get caught next time
+            for k in keys.iter_mut() {
+                if k.update_credential(&auth_result) == Some(true) {
+                    app_state.users.update_passkey(k).await?;
+                }
+            }
 
             // Set both user_id and username in session
             session.insert("user_id", user_unique_id).await?;
@@ -196,8 +185,8 @@ pub async fn finish_authentication(
             StatusCode::OK
         }
         Err(e) => {
-            tracing::info!("challenge_register -> {:?}", e);
-            StatusCode::BAD_REQUEST
+            tracing::info!("challenge_authenticate -> {:?}", e);
+            return Err(ApiError::AuthenticationFailed);
         }
     };
     tracing::info!("Authentication Successful!");
@@ -1,28 +1,63 @@
+use std::collections::HashSet;
+
+use axum::body::Bytes;
 use axum::extract::State;
 use axum::{extract::Path, response::IntoResponse, Json};
-use http::StatusCode;
+use chrono::Utc;
 use tower_sessions::Session;
 use uuid::Uuid;
 
-use crate::error::WebauthnError;
-use crate::models::poll::{CreatePollRequest, Poll, PollOption, VoteRequest};
+use crate::config::poll_undo_window;
+use crate::error::{ApiError, FieldError};
+use crate::extract::{ApiJson, ApiQuery};
+use crate::models::listing::{PollCursor, PollFilter, PollListQuery, PollPage};
+use crate::models::poll::{
+    new_share_token, BallotPrivacy, CreatePollRequest, DeletedPoll, Poll, PollAccess, PollEvent,
+    PollOption, PollState, PollVisibility, PollVoter, ReopenPollRequest, ShareTokenQuery,
+    UpdatePollRequest, VoteOutcome, VoteRequest, VotedPoll, VotingMethod,
+};
+use crate::models::results::{PollResults, ResultsQuery};
 use crate::state::AppState;
+use crate::tally::{engine, Ballot};
 
 pub async fn create_poll(
     State(state): State<AppState>,
     session: Session,
-    Json(req): Json<CreatePollRequest>,
-) -> Result<impl IntoResponse, WebauthnError> {
-    let username = session
+    ApiJson(req): ApiJson<CreatePollRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
         .get("username")
-        .await
-        .map_err(|_| WebauthnError::NotAuthenticated)?
-        .ok_or(WebauthnError::NotAuthenticated)?;
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let poll = create_poll_as(&state, username, req).await?;
+    Ok(Json(poll))
+}
+
+/// Validates `req` against the configured limits and stores the new poll.
+/// Every creation path goes through here so they all enforce the same rules.
+pub async fn create_poll_as(
+    state: &AppState,
+    creator_id: String,
+    req: CreatePollRequest,
+) -> Result<Poll, ApiError> {
+    let req = req
+        .validate(&state.poll_limits)
+        .map_err(ApiError::Validation)?;
 
+    let (min_choices, max_choices) = req.choice_limits();
+    let now = Utc::now();
+    let state_now = if req.draft {
+        PollState::Draft
+    } else if req.opens_at.is_some_and(|opens_at| opens_at > now) {
+        PollState::Scheduled
+    } else {
+        PollState::Open
+    };
     let poll = Poll {
         id: Uuid::new_v4().to_string(),
         title: req.title,
-        creator_id: username,
+        creator_id,
         total_votes: 0,
         options: req
             .options
@@ -31,146 +66,728 @@ pub async fn create_poll(
                 id: Uuid::new_v4().to_string(),
                 text,
                 votes: 0,
+                percentage: 0.0,
             })
             .collect(),
-        created_at: chrono::Utc::now(),
+        created_at: now,
         is_closed: false,
+        allow_vote_change: req.allow_vote_change,
+        results_visibility: req.results_visibility,
+        opens_at: req.opens_at,
+        closes_at: req.closes_at,
+        voting_method: req.voting_method,
+        min_choices,
+        max_choices,
+        max_score: req.max_score,
+        ballot_privacy: req.ballot_privacy,
+        state: state_now,
+        closed_at: None,
+        closed_by: None,
+        visibility: req.visibility,
+        share_token: (req.visibility == PollVisibility::Private).then(new_share_token),
+        allowed_users: req.allowed_users,
     };
 
-    let mut polls = state.polls.lock().await;
-    polls.insert(poll.id.clone(), poll.clone());
+    state.polls.insert_poll(&poll).await?;
 
-    Ok(Json(poll))
+    Ok(poll)
+}
+
+pub async fn list_polls(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<PollListQuery>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: Option<String> = session.get("username").await?;
+    let mut filter = query.into_filter().map_err(ApiError::Validation)?;
+    filter.viewer = username.clone();
+
+    Ok(Json(list_page(&state, filter, username.as_deref()).await?))
 }
 
-pub async fn list_polls(State(state): State<AppState>) -> impl IntoResponse {
-    let polls = state.polls.lock().await;
-    let polls_vec: Vec<Poll> = polls.values().cloned().collect();
-    Json(polls_vec)
+/// Polls created by the session's user. Takes the same query as
+/// `list_polls`, except that `creator` is ignored.
+pub async fn my_polls(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<PollListQuery>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+    let mut filter = query.into_filter().map_err(ApiError::Validation)?;
+    filter.creator = Some(username.clone());
+    filter.viewer = Some(username.clone());
+
+    Ok(Json(list_page(&state, filter, Some(&username)).await?))
+}
+
+/// Polls the session's user voted in, with what they chose.
+pub async fn my_votes(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id: Uuid = session
+        .get("user_id")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+    let username: Option<String> = session.get("username").await?;
+
+    let voted: Vec<VotedPoll> = state
+        .polls
+        .voted_polls(user_id)
+        .await?
+        .into_iter()
+        .map(|(poll, my_ballot)| VotedPoll {
+            poll: poll.view_for(username.as_deref()),
+            my_ballot,
+        })
+        .collect();
+    Ok(Json(voted))
+}
+
+async fn list_page(
+    state: &AppState,
+    mut filter: PollFilter,
+    viewer: Option<&str>,
+) -> Result<PollPage, ApiError> {
+    // Fetch one extra poll to learn whether another page follows.
+    let limit = filter.limit;
+    filter.limit += 1;
+    let mut polls = state.polls.list_polls(&filter).await?;
+    let more = polls.len() > limit;
+    polls.truncate(limit);
+
+    let items: Vec<Poll> = polls.iter().map(|poll| poll.view_for(viewer)).collect();
+    // Built from what the viewer sees, so hidden counts don't leak through it
+    let next_cursor = items
+        .last()
+        .filter(|_| more)
+        .map(|poll| PollCursor::after(poll, filter.sort, viewer).encode());
+    Ok(PollPage { items, next_cursor })
 }
 
 pub async fn get_poll(
     State(state): State<AppState>,
     Path(poll_id): Path<String>,
-) -> Result<impl IntoResponse, WebauthnError> {
-    let polls = state.polls.lock().await;
-    let poll = polls.get(&poll_id).cloned().ok_or(WebauthnError::Unknown)?;
-    Ok(Json(poll))
+    ApiQuery(token): ApiQuery<ShareTokenQuery>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let access = PollAccess::new(session.get("username").await?, token.token);
+    let poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .filter(|poll| poll.is_accessible_by(&access))
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
+    Ok(Json(poll.view_for(access.username.as_deref())))
+}
+
+/// Lets the creator fix a poll after creating it. Until the first vote the
+/// title and options can change freely; afterwards options can only be
+/// appended, so no ballot ever points at a changed option.
+pub async fn update_poll(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+    ApiJson(req): ApiJson<UpdatePollRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let req = req
+        .validate(&state.poll_limits)
+        .map_err(ApiError::Validation)?;
+
+    let poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.clone()))?;
+    check_creator(&poll, &username)?;
+    if poll.is_closed_at(Utc::now()) {
+        return Err(ApiError::PollClosed(poll_id));
+    }
+
+    let has_votes = poll.total_votes > 0;
+    let mut updated = poll.clone();
+
+    if let Some(title) = req.title {
+        if has_votes && title != poll.title {
+            return Err(ApiError::PollHasVotes(poll_id));
+        }
+        updated.title = title;
+    }
+
+    if let Some(edits) = req.options {
+        let mut options = Vec::with_capacity(edits.len());
+        for edit in edits {
+            let option = match edit.id {
+                Some(id) => {
+                    let existing = poll
+                        .options
+                        .iter()
+                        .find(|opt| opt.id == id)
+                        .ok_or(ApiError::OptionNotFound(id))?;
+                    PollOption {
+                        text: edit.text,
+                        ..existing.clone()
+                    }
+                }
+                None => PollOption {
+                    id: Uuid::new_v4().to_string(),
+                    text: edit.text,
+                    votes: 0,
+                    percentage: 0.0,
+                },
+            };
+            options.push(option);
+        }
+
+        let appends_only = options.len() >= poll.options.len()
+            && options
+                .iter()
+                .zip(&poll.options)
+                .all(|(new, old)| new.id == old.id && new.text == old.text);
+        if has_votes && !appends_only {
+            return Err(ApiError::PollHasVotes(poll_id));
+        }
+        updated.options = options;
+    }
+
+    if let Some(visibility) = req.visibility {
+        updated.visibility = visibility;
+    }
+    if let Some(allowed_users) = req.allowed_users {
+        updated.allowed_users = allowed_users;
+    }
+    if updated.visibility == PollVisibility::Private && updated.share_token.is_none() {
+        updated.share_token = Some(new_share_token());
+    }
+
+    let option_count = updated.options.len() as u32;
+    if updated.min_choices > option_count {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "options",
+            "too_few",
+            format!(
+                "This poll needs at least {} options for its minimum choices",
+                updated.min_choices
+            ),
+        )]));
+    }
+    updated.max_choices = updated.max_choices.min(option_count);
+    updated.refresh_tally();
+
+    if !state.polls.update_poll(&updated, has_votes).await? {
+        return Err(ApiError::PollHasVotes(poll_id));
+    }
+
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(updated.clone())));
+
+    Ok(Json(updated.view_for(Some(&username))))
+}
+
+pub async fn get_poll_results(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    ApiQuery(query): ApiQuery<ResultsQuery>,
+    ApiQuery(token): ApiQuery<ShareTokenQuery>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let access = PollAccess::new(session.get("username").await?, token.token);
+    let poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .filter(|poll| poll.is_accessible_by(&access) && query.matches(poll))
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.clone()))?;
+
+    Ok(Json(
+        poll_results(&state, &poll, access.username.as_deref()).await?,
+    ))
+}
+
+/// Tallies `poll` as `viewer` sees it, for both the REST and websocket APIs.
+pub async fn poll_results(
+    state: &AppState,
+    poll: &Poll,
+    viewer: Option<&str>,
+) -> Result<PollResults, ApiError> {
+    if !poll.results_visible_to(viewer) {
+        return Err(ApiError::ResultsHidden(poll.id.clone()));
+    }
+
+    let ballots = state.polls.ballots(&poll.id).await?;
+    let options: Vec<String> = poll.options.iter().map(|opt| opt.id.clone()).collect();
+    let tally = engine(poll.voting_method).count(&options, &ballots);
+
+    Ok(PollResults::from_poll(poll, Utc::now()).with_tally(tally))
+}
+
+/// Lists who voted for what. Creator only, and only on attributed polls.
+pub async fn get_poll_voters(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.clone()))?;
+
+    check_creator(&poll, &username)?;
+    if poll.ballot_privacy == BallotPrivacy::Anonymous {
+        return Err(ApiError::AnonymousBallots(poll_id));
+    }
+
+    let mut voters = Vec::new();
+    for (user_id, ballot) in state.polls.attributed_ballots(&poll_id).await? {
+        voters.push(PollVoter {
+            user_id,
+            username: state.users.find_username(user_id).await?,
+            ballot,
+        });
+    }
+    voters.sort_by(|a, b| a.username.cmp(&b.username));
+
+    Ok(Json(voters))
 }
 
 pub async fn vote_poll(
     State(state): State<AppState>,
     Path(poll_id): Path<String>,
-    Json(req): Json<VoteRequest>,
-) -> Result<impl IntoResponse, WebauthnError> {
-    let mut polls = state.polls.lock().await;
-    let poll = polls.get_mut(&poll_id).ok_or(WebauthnError::Unknown)?;
+    ApiQuery(token): ApiQuery<ShareTokenQuery>,
+    session: Session,
+    ApiJson(req): ApiJson<VoteRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id: Uuid = session
+        .get("user_id")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let access = PollAccess::new(session.get("username").await?, token.token);
+
+    let poll = cast_vote(&state, &poll_id, user_id, &access, req.into_ballot()).await?;
+    Ok(Json(poll.view_for(access.username.as_deref())))
+}
+
+/// Casts `user_id`'s ballot and broadcasts the result. Shared by the REST
+/// and websocket vote paths so both enforce the same rules; `access` must
+/// be allowed to open the poll.
+pub async fn cast_vote(
+    state: &AppState,
+    poll_id: &str,
+    user_id: Uuid,
+    access: &PollAccess,
+    ballot: Ballot,
+) -> Result<Poll, ApiError> {
+    let poll = state
+        .polls
+        .get_poll(poll_id)
+        .await?
+        .filter(|poll| poll.is_accessible_by(access))
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
 
-    if poll.is_closed {
-        return Err(WebauthnError::Unknown);
+    let now = Utc::now();
+    if poll.is_closed_at(now) {
+        return Err(ApiError::PollClosed(poll_id.to_string()));
+    }
+    if poll.state == PollState::Draft || poll.is_pending_at(now) {
+        return Err(ApiError::PollNotOpen(poll_id.to_string()));
     }
 
-    if let Some(option) = poll.options.iter_mut().find(|opt| opt.id == req.option_id) {
-        option.votes += 1;
-        // Broadcast the update
-        let _ = state.poll_updates.send((poll_id.clone(), poll.clone()));
-        Ok(Json(poll.clone()))
-    } else {
-        Err(WebauthnError::Unknown)
+    let mut ballot = ballot;
+    check_ballot(&poll, &ballot)?;
+
+    // Only ranked ballots keep the voter's order; others are stored in poll order
+    if !poll.voting_method.is_ranked() {
+        ballot.option_ids = poll
+            .options
+            .iter()
+            .filter(|opt| ballot.option_ids.contains(&opt.id))
+            .map(|opt| opt.id.clone())
+            .collect();
     }
+
+    let outcome = state
+        .polls
+        .cast_ballot(poll_id, user_id, &ballot, poll.allow_vote_change)
+        .await?;
+
+    match outcome {
+        VoteOutcome::AlreadyVoted => return Err(ApiError::AlreadyVoted(poll_id.to_string())),
+        VoteOutcome::PollNotFound => return Err(ApiError::PollNotFound(poll_id.to_string())),
+        VoteOutcome::Unchanged => return Ok(poll),
+        VoteOutcome::Recorded | VoteOutcome::Changed { .. } => {}
+    }
+
+    let poll = state
+        .polls
+        .get_poll(poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
+
+    // Broadcast the update
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(poll.clone())));
+    Ok(poll)
+}
+
+/// Checks a ballot against the poll's options, choice limits and method.
+fn check_ballot(poll: &Poll, ballot: &Ballot) -> Result<(), ApiError> {
+    let option_ids = &ballot.option_ids;
+    if let Some(unknown) = option_ids
+        .iter()
+        .find(|id| !poll.options.iter().any(|opt| opt.id == **id))
+    {
+        return Err(ApiError::OptionNotFound(unknown.clone()));
+    }
+
+    let distinct: HashSet<&String> = option_ids.iter().collect();
+    let error = if distinct.len() != option_ids.len() {
+        FieldError::new(
+            "option_ids",
+            "duplicate",
+            "An option can only be selected once",
+        )
+    } else if option_ids.len() < poll.min_choices as usize {
+        FieldError::new(
+            "option_ids",
+            "too_few",
+            format!("Select at least {} options", poll.min_choices),
+        )
+    } else if option_ids.len() > poll.max_choices as usize {
+        FieldError::new(
+            "option_ids",
+            "too_many",
+            format!("Select at most {} options", poll.max_choices),
+        )
+    } else if poll.voting_method == VotingMethod::Score && ballot.scores.is_empty() {
+        FieldError::new("scores", "required", "Score each option you vote for")
+    } else if poll.voting_method != VotingMethod::Score && !ballot.scores.is_empty() {
+        FieldError::new("scores", "not_allowed", "This poll does not take scores")
+    } else if ballot.scores.values().any(|score| *score > poll.max_score) {
+        FieldError::new(
+            "scores",
+            "out_of_range",
+            format!("Scores must be between 0 and {}", poll.max_score),
+        )
+    } else {
+        return Ok(());
+    };
+
+    Err(ApiError::Validation(vec![error]))
 }
 
 pub async fn close_poll(
     State(state): State<AppState>,
     Path(poll_id): Path<String>,
     session: Session,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let poll = own_poll(&state, &poll_id, &username).await?;
+    let poll = change_state(&state, poll, PollState::Closed, &username).await?;
+    Ok(Json(poll))
+}
+
+/// Opens a closed poll again, or schedules it if `opens_at` is still ahead.
+/// A `closes_at` that has already passed is cleared unless a new one is given.
+pub async fn reopen_poll(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
     let username: String = session
         .get("username")
         .await?
-        .ok_or(WebauthnError::NotAuthenticated)?;
+        .ok_or(ApiError::NotAuthenticated)?;
 
-    let mut polls = state.polls.lock().await;
-    let poll = polls.get_mut(&poll_id).ok_or(WebauthnError::Unknown)?;
+    let req: ReopenPollRequest = if body.is_empty() {
+        ReopenPollRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| {
+            ApiError::Validation(vec![FieldError::new("body", "invalid", e.to_string())])
+        })?
+    };
 
-    // Verify that the user is the poll creator
-    if poll.creator_id != username {
-        return Err(WebauthnError::Unauthorized);
+    let mut poll = own_poll(&state, &poll_id, &username).await?;
+    let now = Utc::now();
+    match req.closes_at {
+        Some(closes_at) if closes_at <= now => {
+            return Err(ApiError::Validation(vec![FieldError::new(
+                "closes_at",
+                "in_past",
+                "Closing time must be in the future",
+            )]));
+        }
+        Some(closes_at) => poll.closes_at = Some(closes_at),
+        None if poll.closes_at.is_some_and(|closes_at| closes_at <= now) => {
+            poll.closes_at = None;
+        }
+        None => {}
     }
 
-    poll.is_closed = true;
+    let next = if poll.is_pending_at(now) {
+        PollState::Scheduled
+    } else {
+        PollState::Open
+    };
+    let poll = change_state(&state, poll, next, &username).await?;
+    Ok(Json(poll))
+}
 
-    // Broadcast the update
-    let _ = state.poll_updates.send((poll_id.clone(), poll.clone()));
+/// Replaces a private poll's share token; links with the old one stop working.
+pub async fn rotate_share_token(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let mut poll = own_poll(&state, &poll_id, &username).await?;
+    if poll.visibility != PollVisibility::Private {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "visibility",
+            "not_private",
+            "Only private polls have a share token",
+        )]));
+    }
+
+    let share_token = new_share_token();
+    state.polls.set_share_token(&poll_id, &share_token).await?;
+    poll.share_token = Some(share_token);
+
+    // Sockets subscribed with the old token are told the poll is gone
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(poll.clone())));
 
-    Ok(Json(poll.clone()))
+    Ok(Json(poll.view_for(Some(&username))))
 }
 
-pub async fn reset_poll_votes(
+/// Makes a draft visible, scheduled or open depending on `opens_at`.
+pub async fn publish_poll(
     State(state): State<AppState>,
     Path(poll_id): Path<String>,
     session: Session,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     let username: String = session
         .get("username")
         .await?
-        .ok_or(WebauthnError::NotAuthenticated)?;
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let poll = own_poll(&state, &poll_id, &username).await?;
+    let now = Utc::now();
+    if poll.closes_at.is_some_and(|closes_at| closes_at <= now) {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "closes_at",
+            "in_past",
+            "Closing time must be in the future",
+        )]));
+    }
 
-    let mut polls = state.polls.lock().await;
-    let poll = polls.get_mut(&poll_id).ok_or(WebauthnError::Unknown)?;
+    let next = if poll.is_pending_at(now) {
+        PollState::Scheduled
+    } else {
+        PollState::Open
+    };
+    let poll = change_state(&state, poll, next, &username).await?;
+    Ok(Json(poll))
+}
 
-    // Verify that the user is the poll creator
-    if poll.creator_id != username {
-        return Err(WebauthnError::Unauthorized);
+/// Puts a closed poll away for good.
+pub async fn archive_poll(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let poll = own_poll(&state, &poll_id, &username).await?;
+    let poll = change_state(&state, poll, PollState::Archived, &username).await?;
+    Ok(Json(poll))
+}
+
+/// Loads a poll the session user created.
+async fn own_poll(state: &AppState, poll_id: &str, username: &str) -> Result<Poll, ApiError> {
+    let poll = state
+        .polls
+        .get_poll(poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
+
+    check_creator(&poll, username)?;
+    Ok(poll)
+}
+
+/// Lets only the creator through. Callers who can't see the poll get
+/// `PollNotFound`, as on every read, so the refusal doesn't reveal it exists.
+fn check_creator(poll: &Poll, username: &str) -> Result<(), ApiError> {
+    if poll.creator_id == username {
+        return Ok(());
+    }
+    if poll.is_accessible_by(&PollAccess::new(Some(username.to_string()), None)) {
+        Err(ApiError::Forbidden)
+    } else {
+        Err(ApiError::PollNotFound(poll.id.clone()))
     }
+}
+
+/// Moves `poll` to `next` if its lifecycle allows, saves and broadcasts it.
+async fn change_state(
+    state: &AppState,
+    mut poll: Poll,
+    next: PollState,
+    actor: &str,
+) -> Result<Poll, ApiError> {
+    let from = poll.state;
+    let refused = |poll: &Poll| ApiError::InvalidTransition {
+        poll_id: poll.id.clone(),
+        from,
+        to: next,
+    };
+
+    poll.transition(next, Some(actor), Utc::now())
+        .map_err(|_| refused(&poll))?;
+    // Someone else moved it first
+    if !state.polls.set_state(&poll, from).await? {
+        return Err(refused(&poll));
+    }
+
+    tracing::info!(
+        "Poll {} went from {} to {} by {}",
+        poll.id,
+        from.as_str(),
+        next.as_str(),
+        actor
+    );
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(poll.clone())));
+
+    Ok(poll)
+}
+
+pub async fn reset_poll_votes(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let mut poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
+
+    check_creator(&poll, &username)?;
 
     // Reset votes for all options
+    state.polls.reset_votes(&poll_id).await?;
     for option in poll.options.iter_mut() {
         option.votes = 0;
     }
+    poll.refresh_tally();
 
     // Broadcast the update
-    let _ = state.poll_updates.send((poll_id.clone(), poll.clone()));
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(poll.clone())));
 
-    Ok(Json(poll.clone()))
+    Ok(Json(poll))
 }
 
 pub async fn delete_poll(
     State(state): State<AppState>,
     Path(poll_id): Path<String>,
     session: Session,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     let username: String = session
         .get("username")
         .await?
-        .ok_or(WebauthnError::NotAuthenticated)?;
+        .ok_or(ApiError::NotAuthenticated)?;
 
-    let mut polls = state.polls.lock().await;
+    let poll = state
+        .polls
+        .get_poll(&poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))?;
 
-    // Clone the poll before removing it
-    let poll = polls.get(&poll_id).cloned().ok_or(WebauthnError::Unknown)?;
+    check_creator(&poll, &username)?;
 
-    // Verify that the user is the poll creator
-    if poll.creator_id != username {
-        return Err(WebauthnError::Unauthorized);
+    // Hide the poll; it is purged once the undo window passes
+    let deleted_at = Utc::now();
+    state.polls.soft_delete_poll(&poll_id, deleted_at).await?;
+
+    // Broadcast the deletion
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Deleted(Box::new(poll.clone())));
+
+    Ok(Json(DeletedPoll {
+        poll_id,
+        deleted_at,
+        restorable_until: deleted_at + poll_undo_window(),
+    }))
+}
+
+pub async fn restore_poll(
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let username: String = session
+        .get("username")
+        .await?
+        .ok_or(ApiError::NotAuthenticated)?;
+
+    let (poll, deleted_at) = state
+        .polls
+        .get_deleted_poll(&poll_id)
+        .await?
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.clone()))?;
+
+    check_creator(&poll, &username)?;
+
+    // Past the undo window the poll is as good as purged
+    if Utc::now() > deleted_at + poll_undo_window() {
+        return Err(ApiError::PollNotFound(poll_id));
     }
 
-    // Remove the poll
-    polls.remove(&poll_id);
+    state.polls.restore_poll(&poll_id).await?;
 
-    // Broadcast the deletion
-    let _ = state.poll_updates.send((
-        poll_id.clone(),
-        Poll {
-            id: poll_id,
-            title: "".to_string(),
-            creator_id: "".to_string(),
-            options: vec![],
-            total_votes: 0,
-            created_at: chrono::Utc::now(),
-            is_closed: true,
-        },
-    ));
-
-    Ok(StatusCode::NO_CONTENT)
+    // Broadcast the poll again so subscribers pick it back up
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(poll.clone())));
+
+    Ok(Json(poll))
 }
@@ -1,7 +1,12 @@
 pub mod config;
+pub mod db;
 pub mod error;
+pub mod extract;
 pub mod handlers;
 pub mod models;
 pub mod routes;
 pub mod state;
+pub mod store;
+pub mod tally;
+pub mod tasks;
 pub mod websocket;
@@ -1,9 +1,10 @@
 #[allow(dead_code, unused)]
 use polling::{
     config::{setup_cors, setup_tracing},
+    db,
     routes::create_router,
     state::AppState,
-    websocket::start_ws_server,
+    tasks::{advance_poll_schedules, purge_deleted_polls},
 };
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
@@ -14,15 +15,18 @@ async fn main() {
     dotenv::dotenv().ok();
     setup_tracing();
 
-    let app_state = AppState::new();
-    let ws_app_state = app_state.clone();
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool = db::connect(&database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    let app_state = AppState::mysql(pool);
     let session_store = MemoryStore::default();
 
     let app = create_router(app_state.clone(), session_store);
 
-    tokio::spawn(async move {
-        start_ws_server(ws_app_state).await;
-    });
+    tokio::spawn(purge_deleted_polls(app_state.clone()));
+    tokio::spawn(advance_poll_schedules(app_state.clone()));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     let listener = TcpListener::bind(&addr)
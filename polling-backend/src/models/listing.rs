@@ -0,0 +1,197 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::poll::{Poll, PollState};
+use crate::error::FieldError;
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollSort {
+    #[default]
+    Newest,
+    MostVotes,
+    /// Open polls with a deadline first, soonest deadline first.
+    ClosingSoon,
+}
+
+impl PollSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PollSort::Newest => "newest",
+            PollSort::MostVotes => "most_votes",
+            PollSort::ClosingSoon => "closing_soon",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "newest" => Some(PollSort::Newest),
+            "most_votes" => Some(PollSort::MostVotes),
+            "closing_soon" => Some(PollSort::ClosingSoon),
+            _ => None,
+        }
+    }
+
+    /// Where `poll` falls in this order for `viewer`. Listings are ascending
+    /// by `(sort_key, id)`, so descending orders negate their value. Votes
+    /// `viewer` may not see yet count as none, as in `Poll::view_for`.
+    pub fn sort_key(&self, poll: &Poll, viewer: Option<&str>) -> i64 {
+        match self {
+            PollSort::Newest => -poll.created_at.timestamp_micros(),
+            PollSort::MostVotes if poll.results_visible_to(viewer) => -i64::from(poll.total_votes),
+            PollSort::MostVotes => 0,
+            PollSort::ClosingSoon => match poll.closes_at {
+                Some(closes_at) if !poll.is_closed => closes_at.timestamp_micros(),
+                _ => i64::MAX,
+            },
+        }
+    }
+}
+
+/// Query string accepted by `GET /api/polls`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PollListQuery {
+    /// Only polls in this lifecycle state.
+    pub status: Option<PollState>,
+    /// Only polls created by this username.
+    pub creator: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    /// Case-insensitive search within titles.
+    pub q: Option<String>,
+    #[serde(default)]
+    pub sort: PollSort,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl PollListQuery {
+    pub fn into_filter(self) -> Result<PollFilter, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            errors.push(FieldError::new(
+                "limit",
+                "out_of_range",
+                format!("Limit must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+
+        let after = match self.cursor.as_deref().map(PollCursor::decode) {
+            Some(Some(cursor)) if cursor.sort == self.sort => Some(cursor),
+            Some(_) => {
+                errors.push(FieldError::new(
+                    "cursor",
+                    "invalid",
+                    "Cursor is malformed or belongs to another sort order",
+                ));
+                None
+            }
+            None => None,
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(PollFilter {
+            status: self.status,
+            creator: self.creator,
+            created_after: self.created_after,
+            search: self
+                .q
+                .map(|q| q.trim().to_string())
+                .filter(|q| !q.is_empty()),
+            sort: self.sort,
+            after,
+            limit,
+            viewer: None,
+        })
+    }
+}
+
+/// A validated listing request, as handed to the store.
+#[derive(Clone, Debug, Default)]
+pub struct PollFilter {
+    pub status: Option<PollState>,
+    pub creator: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub search: Option<String>,
+    pub sort: PollSort,
+    /// Only polls after this position in `sort` order.
+    pub after: Option<PollCursor>,
+    pub limit: usize,
+    /// Username of whoever is listing; drafts and private polls are only
+    /// listed for their creator.
+    pub viewer: Option<String>,
+}
+
+impl PollFilter {
+    /// Whether `poll` passes every filter, ignoring the cursor.
+    pub fn matches(&self, poll: &Poll) -> bool {
+        poll.is_listed_for(self.viewer.as_deref())
+            && self.status.is_none_or(|status| status == poll.state)
+            && self
+                .creator
+                .as_ref()
+                .is_none_or(|creator| *creator == poll.creator_id)
+            && self
+                .created_after
+                .is_none_or(|after| poll.created_at > after)
+            && self
+                .search
+                .as_ref()
+                .is_none_or(|search| poll.title.to_lowercase().contains(&search.to_lowercase()))
+    }
+}
+
+/// Position of the last poll on a page, handed out as an opaque token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PollCursor {
+    pub sort: PollSort,
+    pub key: i64,
+    pub id: String,
+}
+
+impl PollCursor {
+    pub fn after(poll: &Poll, sort: PollSort, viewer: Option<&str>) -> Self {
+        Self {
+            sort,
+            key: sort.sort_key(poll, viewer),
+            id: poll.id.clone(),
+        }
+    }
+
+    /// Whether `poll` comes after this cursor in `viewer`'s listing.
+    pub fn precedes(&self, poll: &Poll, viewer: Option<&str>) -> bool {
+        (self.sort.sort_key(poll, viewer), poll.id.as_str()) > (self.key, self.id.as_str())
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", self.sort.as_str(), self.key, self.id))
+    }
+
+    pub fn decode(token: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+        let mut parts = raw.splitn(3, ':');
+        Some(Self {
+            sort: PollSort::parse(parts.next()?)?,
+            key: parts.next()?.parse().ok()?,
+            id: parts.next()?.to_string(),
+        })
+    }
+}
+
+/// One page of polls.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PollPage {
+    pub items: Vec<Poll>,
+    /// Pass back as `cursor` for the next page; absent on the last page.
+    pub next_cursor: Option<String>,
+}
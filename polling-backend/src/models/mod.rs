@@ -1,2 +1,3 @@
+pub mod listing;
 pub mod poll;
-pub mod user;
+pub mod results;
@@ -1,7 +1,15 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+
+use crate::config::PollLimits;
+use crate::error::FieldError;
+use crate::tally::Ballot;
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Poll {
     pub id: String,
     pub title: String,
@@ -10,22 +18,735 @@ pub struct Poll {
     pub created_at: DateTime<Utc>,
     pub is_closed: bool,
     pub total_votes: i32,
+    /// Whether voters may move their ballot to another option.
+    #[serde(default)]
+    pub allow_vote_change: bool,
+    #[serde(default)]
+    pub results_visibility: ResultsVisibility,
+    /// Votes are refused before this time.
+    #[serde(default)]
+    pub opens_at: Option<DateTime<Utc>>,
+    /// The poll closes itself at this time.
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub voting_method: VotingMethod,
+    /// How many options a single ballot must select, or rank.
+    #[serde(default = "default_choices")]
+    pub min_choices: u32,
+    #[serde(default = "default_choices")]
+    pub max_choices: u32,
+    /// Highest score per option on score polls.
+    #[serde(default = "default_max_score")]
+    pub max_score: u32,
+    #[serde(default)]
+    pub ballot_privacy: BallotPrivacy,
+    #[serde(default)]
+    pub state: PollState,
+    /// When the poll last closed; cleared when it reopens.
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Who closed it; `None` when it closed itself at `closes_at`.
+    #[serde(default)]
+    pub closed_by: Option<String>,
+    #[serde(default)]
+    pub visibility: PollVisibility,
+    /// Grants access to a private poll. Only shown to the creator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_token: Option<String>,
+    /// Usernames that may open a private poll without the token. Only shown
+    /// to the creator.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_users: Vec<String>,
+}
+
+fn default_choices() -> u32 {
+    1
+}
+
+fn default_max_score() -> u32 {
+    5
+}
+
+/// How ballots are cast and counted; see `crate::tally` for the counting.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMethod {
+    /// Each selected option gets one vote.
+    #[default]
+    Plurality,
+    /// Voters approve any number of options.
+    Approval,
+    /// Voters give each option up to `max_score` points.
+    Score,
+    /// Ballots rank options and are counted by instant runoff.
+    RankedChoice,
+    /// Ballots rank options and are compared pairwise (Schulze).
+    Condorcet,
+}
+
+impl VotingMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VotingMethod::Plurality => "plurality",
+            VotingMethod::Approval => "approval",
+            VotingMethod::Score => "score",
+            VotingMethod::RankedChoice => "ranked_choice",
+            VotingMethod::Condorcet => "condorcet",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "plurality" => Some(VotingMethod::Plurality),
+            "approval" => Some(VotingMethod::Approval),
+            "score" => Some(VotingMethod::Score),
+            "ranked_choice" => Some(VotingMethod::RankedChoice),
+            "condorcet" => Some(VotingMethod::Condorcet),
+            _ => None,
+        }
+    }
+
+    /// Whether the order of a ballot's option ids carries meaning.
+    pub fn is_ranked(&self) -> bool {
+        matches!(self, VotingMethod::RankedChoice | VotingMethod::Condorcet)
+    }
+}
+
+/// Whether ballots remember who cast them.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotPrivacy {
+    /// The creator can list who voted for what.
+    #[default]
+    Attributed,
+    /// Only the fact that someone voted is stored, apart from their ballot.
+    Anonymous,
+}
+
+impl BallotPrivacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BallotPrivacy::Attributed => "attributed",
+            BallotPrivacy::Anonymous => "anonymous",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "attributed" => Some(BallotPrivacy::Attributed),
+            "anonymous" => Some(BallotPrivacy::Anonymous),
+            _ => None,
+        }
+    }
+}
+
+/// Where a poll is in its lifecycle. `is_closed` mirrors `Closed` and
+/// `Archived` for older clients.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollState {
+    /// Not published yet.
+    Draft,
+    /// Published, waiting for `opens_at`.
+    Scheduled,
+    #[default]
+    Open,
+    Closed,
+    /// Closed for good and kept for the record.
+    Archived,
+}
+
+impl PollState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PollState::Draft => "draft",
+            PollState::Scheduled => "scheduled",
+            PollState::Open => "open",
+            PollState::Closed => "closed",
+            PollState::Archived => "archived",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(PollState::Draft),
+            "scheduled" => Some(PollState::Scheduled),
+            "open" => Some(PollState::Open),
+            "closed" => Some(PollState::Closed),
+            "archived" => Some(PollState::Archived),
+            _ => None,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self, PollState::Closed | PollState::Archived)
+    }
+
+    /// The lifecycle's allowed moves. Everything else is refused.
+    pub fn can_become(&self, next: PollState) -> bool {
+        use PollState::*;
+        matches!(
+            (self, next),
+            (Draft, Scheduled)
+                | (Draft, Open)
+                | (Scheduled, Open)
+                | (Scheduled, Closed)
+                | (Open, Closed)
+                | (Closed, Open)
+                | (Closed, Scheduled)
+                | (Closed, Archived)
+        )
+    }
+}
+
+/// Who can find and open a poll.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollVisibility {
+    /// Listed and open to everyone.
+    #[default]
+    Public,
+    /// Never listed; opened with the share token or by allowed users.
+    Private,
+}
+
+impl PollVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PollVisibility::Public => "public",
+            PollVisibility::Private => "private",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "public" => Some(PollVisibility::Public),
+            "private" => Some(PollVisibility::Private),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// Who is asking for a poll: the signed in username and any share token
+/// they presented.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PollAccess {
+    pub username: Option<String>,
+    pub share_token: Option<String>,
+}
+
+impl PollAccess {
+    pub fn new(username: Option<String>, share_token: Option<String>) -> Self {
+        Self {
+            username,
+            share_token,
+        }
+    }
+}
+
+/// `?token=` on requests for a private poll.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ShareTokenQuery {
+    pub token: Option<String>,
+}
+
+/// A fresh, unguessable share token.
+pub fn new_share_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// When voters other than the creator may see the results endpoint.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultsVisibility {
+    #[default]
+    Always,
+    AfterClose,
+}
+
+impl ResultsVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultsVisibility::Always => "always",
+            ResultsVisibility::AfterClose => "after_close",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "always" => Some(ResultsVisibility::Always),
+            "after_close" => Some(ResultsVisibility::AfterClose),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct PollOption {
     pub id: String,
     pub text: String,
     pub votes: i32,
+    /// Share of `total_votes`, 0-100 rounded to two decimals.
+    #[serde(default)]
+    pub percentage: f64,
+}
+
+impl Poll {
+    /// Recomputes `total_votes` and every option's percentage from the
+    /// option vote counts, which are the only source of truth.
+    pub fn refresh_tally(&mut self) {
+        self.total_votes = self.options.iter().map(|opt| opt.votes).sum();
+        for option in self.options.iter_mut() {
+            option.percentage = if self.total_votes == 0 {
+                0.0
+            } else {
+                (option.votes as f64 * 10_000.0 / self.total_votes as f64).round() / 100.0
+            };
+        }
+    }
+
+    /// Whether `now` falls before the scheduled opening.
+    pub fn is_pending_at(&self, now: DateTime<Utc>) -> bool {
+        self.opens_at.is_some_and(|opens_at| now < opens_at)
+    }
+
+    /// Whether the poll is closed, either manually or by reaching `closes_at`.
+    pub fn is_closed_at(&self, now: DateTime<Utc>) -> bool {
+        self.is_closed || self.closes_at.is_some_and(|closes_at| now >= closes_at)
+    }
+
+    /// Moves the poll to `next`, recording who closed it and when.
+    /// Returns the refused target if the lifecycle does not allow the move.
+    pub fn transition(
+        &mut self,
+        next: PollState,
+        actor: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<(), PollState> {
+        if !self.state.can_become(next) {
+            return Err(next);
+        }
+        if next == PollState::Closed {
+            self.closed_at = Some(at);
+            self.closed_by = actor.map(str::to_string);
+        } else if !next.is_closed() {
+            self.closed_at = None;
+            self.closed_by = None;
+        }
+        self.state = next;
+        self.is_closed = next.is_closed();
+        Ok(())
+    }
+
+    /// Whether `viewer` may see the poll at all; drafts are the creator's alone.
+    pub fn is_visible_to(&self, viewer: Option<&str>) -> bool {
+        self.state != PollState::Draft || viewer == Some(self.creator_id.as_str())
+    }
+
+    /// Whether the poll shows up in listings for `viewer`. Drafts and
+    /// private polls only appear for their creator.
+    pub fn is_listed_for(&self, viewer: Option<&str>) -> bool {
+        viewer == Some(self.creator_id.as_str())
+            || (self.state != PollState::Draft && self.visibility == PollVisibility::Public)
+    }
+
+    /// Whether `access` may open the poll: drafts only by their creator,
+    /// private polls also by allowed users or with the share token.
+    pub fn is_accessible_by(&self, access: &PollAccess) -> bool {
+        self.is_visible_to(access.username.as_deref()) && self.is_shared_with(access)
+    }
+
+    /// Whether the poll is public, or `access` names its creator, an allowed
+    /// user or the current share token. Ignores the draft rule.
+    pub fn is_shared_with(&self, access: &PollAccess) -> bool {
+        let viewer = access.username.as_deref();
+        self.visibility == PollVisibility::Public
+            || viewer == Some(self.creator_id.as_str())
+            || viewer.is_some_and(|viewer| self.allowed_users.iter().any(|user| user == viewer))
+            || self
+                .share_token
+                .as_deref()
+                .is_some_and(|token| access.share_token.as_deref() == Some(token))
+    }
+
+    /// Whether `viewer` (a username, if signed in) may see vote counts yet.
+    pub fn results_visible_to(&self, viewer: Option<&str>) -> bool {
+        self.is_closed
+            || self.results_visibility == ResultsVisibility::Always
+            || viewer == Some(self.creator_id.as_str())
+    }
+
+    /// Copy of the poll to show `viewer`, with counts zeroed while hidden
+    /// and the sharing settings left to the creator.
+    pub fn view_for(&self, viewer: Option<&str>) -> Poll {
+        let mut poll = self.clone();
+        if viewer != Some(self.creator_id.as_str()) {
+            poll.share_token = None;
+            poll.allowed_users = Vec::new();
+        }
+        if !poll.results_visible_to(viewer) {
+            for option in poll.options.iter_mut() {
+                option.votes = 0;
+            }
+            poll.refresh_tally();
+        }
+        poll
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CreatePollRequest {
     pub title: String,
     pub options: Vec<String>,
+    #[serde(default)]
+    pub allow_vote_change: bool,
+    #[serde(default)]
+    pub results_visibility: ResultsVisibility,
+    #[serde(default)]
+    pub opens_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub voting_method: VotingMethod,
+    /// Defaults to 1.
+    #[serde(default)]
+    pub min_choices: Option<u32>,
+    /// Defaults to 1 on plurality polls and to every option otherwise.
+    #[serde(default)]
+    pub max_choices: Option<u32>,
+    #[serde(default = "default_max_score")]
+    pub max_score: u32,
+    #[serde(default)]
+    pub ballot_privacy: BallotPrivacy,
+    /// Keep the poll hidden and closed to votes until it is published.
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub visibility: PollVisibility,
+    /// Usernames that may open a private poll without its share token.
+    #[serde(default)]
+    pub allowed_users: Vec<String>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Trims surrounding whitespace and applies NFC so visually identical
+/// strings compare and count the same.
+fn normalize_text(text: &str) -> String {
+    text.trim().nfc().collect()
+}
+
+/// Trims usernames and drops blanks and repeats, keeping the first order.
+fn normalize_usernames(usernames: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    *usernames = usernames
+        .iter()
+        .map(|username| username.trim().to_string())
+        .filter(|username| !username.is_empty() && seen.insert(username.clone()))
+        .collect();
+}
+
+fn validate_title(title: &mut String, limits: &PollLimits, errors: &mut Vec<FieldError>) {
+    *title = normalize_text(title);
+    if title.is_empty() {
+        errors.push(FieldError::new("title", "required", "Title is required"));
+    } else if title.chars().count() > limits.max_title_len {
+        errors.push(FieldError::new(
+            "title",
+            "too_long",
+            format!("Title must be at most {} characters", limits.max_title_len),
+        ));
+    }
+}
+
+/// Normalizes option texts in place and checks their count, length and
+/// case-insensitive uniqueness.
+fn validate_options<'a>(
+    options: impl ExactSizeIterator<Item = &'a mut String>,
+    limits: &PollLimits,
+    errors: &mut Vec<FieldError>,
+) {
+    if options.len() < limits.min_options {
+        errors.push(FieldError::new(
+            "options",
+            "too_few",
+            format!("At least {} options are required", limits.min_options),
+        ));
+    } else if options.len() > limits.max_options {
+        errors.push(FieldError::new(
+            "options",
+            "too_many",
+            format!("At most {} options are allowed", limits.max_options),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for (i, option) in options.enumerate() {
+        let field = format!("options[{}]", i);
+        *option = normalize_text(option);
+        if option.is_empty() {
+            errors.push(FieldError::new(
+                field,
+                "required",
+                "Option text is required",
+            ));
+        } else if option.chars().count() > limits.max_option_len {
+            errors.push(FieldError::new(
+                field,
+                "too_long",
+                format!(
+                    "Option text must be at most {} characters",
+                    limits.max_option_len
+                ),
+            ));
+        } else if !seen.insert(option.to_lowercase()) {
+            errors.push(FieldError::new(
+                field,
+                "duplicate",
+                "Options must be unique",
+            ));
+        }
+    }
+}
+
+impl CreatePollRequest {
+    /// The requested `(min_choices, max_choices)` with defaults applied.
+    pub fn choice_limits(&self) -> (u32, u32) {
+        let default_max = match self.voting_method {
+            VotingMethod::Plurality => 1,
+            _ => self.options.len() as u32,
+        };
+        (
+            self.min_choices.unwrap_or(1),
+            self.max_choices.unwrap_or(default_max),
+        )
+    }
+
+    /// Normalizes the title and options and checks them against `limits`,
+    /// returning the cleaned request or every problem found.
+    pub fn validate(mut self, limits: &PollLimits) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        validate_title(&mut self.title, limits, &mut errors);
+        validate_options(self.options.iter_mut(), limits, &mut errors);
+        normalize_usernames(&mut self.allowed_users);
+
+        let (min_choices, max_choices) = self.choice_limits();
+        if min_choices == 0 {
+            errors.push(FieldError::new(
+                "min_choices",
+                "too_few",
+                "At least one choice must be required",
+            ));
+        } else if min_choices > max_choices {
+            errors.push(FieldError::new(
+                "min_choices",
+                "above_max_choices",
+                "Minimum choices cannot exceed maximum choices",
+            ));
+        } else if max_choices as usize > self.options.len() {
+            errors.push(FieldError::new(
+                "max_choices",
+                "too_many",
+                "Maximum choices cannot exceed the number of options",
+            ));
+        }
+
+        if self.voting_method == VotingMethod::Score && !(1..=100).contains(&self.max_score) {
+            errors.push(FieldError::new(
+                "max_score",
+                "out_of_range",
+                "Maximum score must be between 1 and 100",
+            ));
+        }
+
+        if self.ballot_privacy == BallotPrivacy::Anonymous && self.allow_vote_change {
+            errors.push(FieldError::new(
+                "allow_vote_change",
+                "not_allowed",
+                "Anonymous ballots cannot be changed once cast",
+            ));
+        }
+
+        if let Some(closes_at) = self.closes_at {
+            if self.opens_at.is_some_and(|opens_at| closes_at <= opens_at) {
+                errors.push(FieldError::new(
+                    "closes_at",
+                    "before_opens_at",
+                    "Closing time must be after the opening time",
+                ));
+            } else if closes_at <= Utc::now() {
+                errors.push(FieldError::new(
+                    "closes_at",
+                    "in_past",
+                    "Closing time must be in the future",
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Body of `PATCH /api/polls/{id}`. Omitted fields are left unchanged.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct UpdatePollRequest {
+    pub title: Option<String>,
+    /// The complete new option list, in display order. Existing options
+    /// left out are removed.
+    pub options: Option<Vec<OptionEdit>>,
+    pub visibility: Option<PollVisibility>,
+    /// Replaces the allow-list of a private poll.
+    pub allowed_users: Option<Vec<String>>,
+}
+
+/// One option of an `UpdatePollRequest`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct OptionEdit {
+    /// The existing option this entry keeps; `None` adds a new option.
+    pub id: Option<String>,
+    pub text: String,
+}
+
+impl UpdatePollRequest {
+    /// Normalizes the given fields and checks them against `limits`, like
+    /// `CreatePollRequest::validate`.
+    pub fn validate(mut self, limits: &PollLimits) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if let Some(title) = self.title.as_mut() {
+            validate_title(title, limits, &mut errors);
+        }
+        if let Some(allowed_users) = self.allowed_users.as_mut() {
+            normalize_usernames(allowed_users);
+        }
+
+        if let Some(options) = self.options.as_mut() {
+            validate_options(
+                options.iter_mut().map(|option| &mut option.text),
+                limits,
+                &mut errors,
+            );
+
+            let mut seen = HashSet::new();
+            for (i, option) in options.iter().enumerate() {
+                if let Some(id) = &option.id {
+                    if !seen.insert(id) {
+                        errors.push(FieldError::new(
+                            format!("options[{}]", i),
+                            "duplicate",
+                            "Each existing option can only be listed once",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Optional body of `POST /api/polls/{id}/reopen`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ReopenPollRequest {
+    /// New closing time; must be in the future.
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct VoteRequest {
-    pub option_id: String,
+    /// A single selection, as sent by single-choice clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option_id: Option<String>,
+    /// Every selected option, most preferred first on ranked polls; takes
+    /// precedence over `option_id`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub option_ids: Vec<String>,
+    /// Points per option id on score polls; replaces the other fields.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub scores: HashMap<String, u32>,
+}
+
+impl VoteRequest {
+    pub fn selections(self) -> Vec<String> {
+        if self.option_ids.is_empty() {
+            self.option_id.into_iter().collect()
+        } else {
+            self.option_ids
+        }
+    }
+
+    pub fn into_ballot(self) -> Ballot {
+        if self.scores.is_empty() {
+            Ballot::new(self.selections())
+        } else {
+            Ballot::scored(self.scores.into_iter().collect())
+        }
+    }
+}
+
+/// Change notifications carried on `AppState::poll_updates`.
+#[derive(Clone, Debug)]
+pub enum PollEvent {
+    Updated(Box<Poll>),
+    /// The poll as it was when deleted, so only those who could see it hear
+    /// about it.
+    Deleted(Box<Poll>),
+}
+
+impl PollEvent {
+    pub fn poll_id(&self) -> &str {
+        match self {
+            PollEvent::Updated(poll) | PollEvent::Deleted(poll) => &poll.id,
+        }
+    }
+}
+
+/// Returned by `DELETE /api/polls/{id}`; the poll can be restored until `restorable_until`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DeletedPoll {
+    pub poll_id: String,
+    pub deleted_at: DateTime<Utc>,
+    pub restorable_until: DateTime<Utc>,
+}
+
+/// One entry of `GET /api/polls/{id}/voters`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PollVoter {
+    pub user_id: Uuid,
+    /// `None` if the account no longer exists.
+    pub username: Option<String>,
+    #[serde(flatten)]
+    pub ballot: Ballot,
+}
+
+/// One entry of `GET /api/me/votes`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct VotedPoll {
+    #[serde(flatten)]
+    pub poll: Poll,
+    /// What the user chose; `None` on anonymous polls, where ballots are
+    /// not linked to voters.
+    pub my_ballot: Option<Ballot>,
+}
+
+/// What happened to a voter's ballot when they voted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VoteOutcome {
+    Recorded,
+    Changed {
+        previous_option_ids: Vec<String>,
+    },
+    Unchanged,
+    AlreadyVoted,
+    /// The poll was gone (e.g. deleted) by the time the ballot was written.
+    PollNotFound,
 }
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::poll::Poll;
+use crate::tally::Tally;
+
+/// Filters accepted by `GET /api/polls/{id}/results`. A poll that does not
+/// match every given filter is reported as not found.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ResultsQuery {
+    /// Only match polls that are still open.
+    #[serde(default)]
+    pub live: bool,
+    /// Only match polls that have been closed.
+    #[serde(default)]
+    pub closed: bool,
+    /// Only match polls created by this username.
+    pub creator: Option<String>,
+}
+
+impl ResultsQuery {
+    pub fn matches(&self, poll: &Poll) -> bool {
+        if self.live && poll.is_closed {
+            return false;
+        }
+        if self.closed && !poll.is_closed {
+            return false;
+        }
+        self.creator
+            .as_ref()
+            .is_none_or(|creator| *creator == poll.creator_id)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct OptionResult {
+    pub id: String,
+    pub text: String,
+    pub votes: i32,
+    pub percentage: f64,
+    /// Competition rank: tied options share a rank and the next one skips.
+    pub rank: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PollResults {
+    pub poll_id: String,
+    pub title: String,
+    pub creator_id: String,
+    pub is_closed: bool,
+    pub total_votes: i32,
+    /// Options ordered by rank under the poll's voting method, ties keeping
+    /// poll order.
+    pub options: Vec<OptionResult>,
+    /// Ids of the options sharing the best rank; empty until the first vote.
+    pub leaders: Vec<String>,
+    /// The single leader of a closed poll.
+    pub winner: Option<String>,
+    pub is_tie: bool,
+    pub created_at: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    /// The count by the poll's voting method. On ranked polls option votes
+    /// are first preferences only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tally: Option<Tally>,
+}
+
+impl PollResults {
+    pub fn from_poll(poll: &Poll, generated_at: DateTime<Utc>) -> Self {
+        let mut options: Vec<OptionResult> = poll
+            .options
+            .iter()
+            .map(|opt| OptionResult {
+                id: opt.id.clone(),
+                text: opt.text.clone(),
+                votes: opt.votes,
+                percentage: opt.percentage,
+                rank: 0,
+            })
+            .collect();
+        rank_by(&mut options, |opt| opt.votes as i64);
+
+        let leaders: Vec<String> = if poll.total_votes == 0 {
+            Vec::new()
+        } else {
+            options
+                .iter()
+                .take_while(|opt| opt.rank == 1)
+                .map(|opt| opt.id.clone())
+                .collect()
+        };
+        let is_tie = leaders.len() > 1;
+        let winner = match leaders.as_slice() {
+            [winner] if poll.is_closed => Some(winner.clone()),
+            _ => None,
+        };
+
+        PollResults {
+            poll_id: poll.id.clone(),
+            title: poll.title.clone(),
+            creator_id: poll.creator_id.clone(),
+            is_closed: poll.is_closed,
+            total_votes: poll.total_votes,
+            options,
+            leaders,
+            winner,
+            is_tie,
+            created_at: poll.created_at,
+            generated_at,
+            tally: None,
+        }
+    }
+
+    /// Takes ranks, leaders and the winner from `tally` instead of the raw
+    /// counts, so a runoff or score winner is ranked first even when it had
+    /// fewer first preferences.
+    pub fn with_tally(mut self, tally: Tally) -> Self {
+        let standing = tally.standing();
+        let position = |opt: &OptionResult| standing.iter().position(|(id, _)| *id == opt.id);
+        self.options
+            .sort_by_key(|opt| position(opt).unwrap_or(usize::MAX));
+        rank_by(&mut self.options, |opt| {
+            position(opt).map_or(-1, |i| standing[i].1 as i64)
+        });
+        self.leaders = tally.leaders.clone();
+        self.is_tie = self.leaders.len() > 1;
+        self.winner = tally.winner().filter(|_| self.is_closed).cloned();
+        self.tally = Some(tally);
+        self
+    }
+}
+
+/// Sorts `options` best first by `key` and assigns competition ranks. The
+/// sort is stable, so ties keep their current order.
+fn rank_by(options: &mut [OptionResult], key: impl Fn(&OptionResult) -> i64) {
+    options.sort_by_cached_key(|opt| std::cmp::Reverse(key(opt)));
+
+    let keys: Vec<i64> = options.iter().map(&key).collect();
+    for i in 0..options.len() {
+        options[i].rank = if i > 0 && keys[i] == keys[i - 1] {
+            options[i - 1].rank
+        } else {
+            i as u32 + 1
+        };
+    }
+}
@@ -2,42 +2,49 @@ use crate::{
     config::setup_cors,
     handlers::{
         auth,
-        poll::{close_poll, create_poll, get_poll, list_polls, reset_poll_votes, vote_poll},
+        poll::{
+            archive_poll, close_poll, create_poll, delete_poll, get_poll, get_poll_results,
+            get_poll_voters, list_polls, my_polls, my_votes, publish_poll, reopen_poll,
+            reset_poll_votes, restore_poll, rotate_share_token, update_poll, vote_poll,
+        },
     },
     state::AppState,
 };
 use axum::{
     extract::Extension,
-    http::{HeaderValue, StatusCode},
+    http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Router,
 };
-use http::Method;
 use tower_http::cors::CorsLayer;
 use tower_sessions::{
     cookie::{time::Duration, SameSite},
     Expiry, MemoryStore, SessionManagerLayer,
 };
 
-use super::websocket::poll_websocket_handler;
+use super::websocket::{poll_websocket_handler, websocket_handler};
+
+/// Name of the login session cookie.
+pub const SESSION_COOKIE: &str = "webauthn";
 
 pub fn create_router(app_state: AppState, session_store: MemoryStore) -> Router {
     let cors: CorsLayer = setup_cors();
 
     Router::new()
-        .layer(cors)
         .merge(auth_routes())
         .merge(poll_routes())
         .merge(websocket_routes())
         .layer(Extension(app_state.clone()))
         .layer(
             SessionManagerLayer::new(session_store)
-                .with_name("webauthn")
+                .with_name(SESSION_COOKIE)
                 .with_same_site(SameSite::Strict)
                 .with_secure(true)
                 .with_expiry(Expiry::OnInactivity(Duration::seconds(560))),
         )
+        // Outermost, so preflights are answered before reaching any route
+        .layer(cors)
         .with_state(app_state)
         .fallback(handler_404)
 }
@@ -60,31 +67,27 @@ pub fn poll_routes() -> Router<AppState> {
     Router::new()
         .route("/api/polls", post(create_poll))
         .route("/api/polls", get(list_polls))
-        .route("/api/polls/{id}", get(get_poll))
+        .route(
+            "/api/polls/{id}",
+            get(get_poll).patch(update_poll).delete(delete_poll),
+        )
+        .route("/api/polls/{id}/results", get(get_poll_results))
+        .route("/api/polls/{id}/voters", get(get_poll_voters))
         .route("/api/polls/{id}/vote", post(vote_poll))
         .route("/api/polls/{id}/close", post(close_poll))
+        .route("/api/polls/{id}/publish", post(publish_poll))
+        .route("/api/polls/{id}/share_token", post(rotate_share_token))
+        .route("/api/polls/{id}/reopen", post(reopen_poll))
+        .route("/api/polls/{id}/archive", post(archive_poll))
         .route("/api/polls/{id}/reset", post(reset_poll_votes))
+        .route("/api/polls/{id}/restore", post(restore_poll))
+        .route("/api/me/polls", get(my_polls))
+        .route("/api/me/votes", get(my_votes))
 }
 
 pub fn websocket_routes() -> Router<AppState> {
-    let frontend_urls: Vec<String> = std::env::var("FRONTEND_URL")
-        .unwrap_or_else(|_| "https://frontend.3.108.234.78.sslip.io".to_string())
-        .split(',')
-        .map(|s| s.to_string())
-        .collect();
-
-    let origins = frontend_urls
-        .iter()
-        .map(|url| url.parse::<HeaderValue>().unwrap())
-        .collect::<Vec<HeaderValue>>();
-
-    let websocket_cors = CorsLayer::new()
-        .allow_methods([Method::GET])
-        .allow_origin(origins)
-        .allow_credentials(true);
-
     Router::new()
-        .layer(websocket_cors)
+        .route("/ws/polls", get(websocket_handler))
         .route("/ws/polls/{poll_id}", get(poll_websocket_handler))
 }
 
@@ -1,29 +1,31 @@
 // src/state.rs
-use crate::models::{poll::Poll, user::Data};
+use crate::config::PollLimits;
+use crate::models::poll::PollEvent;
+use crate::store::{MemoryPollStore, MemoryUserStore, MySqlStore, PollStore, UserStore};
 use reqwest::Url;
-use std::collections::HashMap;
+use sqlx::MySqlPool;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::broadcast;
 use webauthn_rs::{Webauthn, WebauthnBuilder};
 
 #[derive(Clone)]
 pub struct AppState {
     pub webauthn: Arc<Webauthn>,
-    pub users: Arc<Mutex<Data>>,
-    pub polls: Arc<Mutex<HashMap<String, Poll>>>,
-    pub poll_updates: broadcast::Sender<(String, Poll)>,
+    pub users: Arc<dyn UserStore>,
+    pub polls: Arc<dyn PollStore>,
+    pub poll_updates: broadcast::Sender<PollEvent>,
+    pub poll_limits: PollLimits,
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new()
+        Self::in_memory()
     }
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        let rp_id =
-            std::env::var("RP_ID").unwrap_or("frontend.3.108.234.78.sslip.io".to_string());
+    pub fn new(polls: Arc<dyn PollStore>, users: Arc<dyn UserStore>) -> Self {
+        let rp_id = std::env::var("RP_ID").unwrap_or("frontend.3.108.234.78.sslip.io".to_string());
         let rp_origin = Url::parse(
             std::env::var("RP_ORIGIN")
                 .unwrap_or("https://frontend.3.108.234.78.sslip.io".to_string())
@@ -38,11 +40,6 @@ impl AppState {
             WebauthnBuilder::new(&rp_id, &rp_origin).expect("Failed to create Webauthn instance");
 
         let webauthn = Arc::new(builder.build().expect("Invalid configuration"));
-        let users = Arc::new(Mutex::new(Data {
-            name_to_id: HashMap::new(),
-            keys: HashMap::new(),
-        }));
-        let polls = Arc::new(Mutex::new(HashMap::new()));
         let (tx, _) = broadcast::channel(100);
 
         AppState {
@@ -50,6 +47,21 @@ impl AppState {
             users,
             polls,
             poll_updates: tx,
+            poll_limits: PollLimits::from_env(),
         }
     }
+
+    /// State backed by process-local maps, used by tests.
+    pub fn in_memory() -> Self {
+        Self::new(
+            Arc::new(MemoryPollStore::default()),
+            Arc::new(MemoryUserStore::default()),
+        )
+    }
+
+    /// State backed by MySQL, used in production.
+    pub fn mysql(pool: MySqlPool) -> Self {
+        let store = Arc::new(MySqlStore::new(pool));
+        Self::new(store.clone(), store)
+    }
 }
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+use super::{PollStore, StoreError, StoreResult, UserStore};
+use crate::models::listing::PollFilter;
+use crate::models::poll::{BallotPrivacy, Poll, PollState, VoteOutcome};
+use crate::tally::Ballot;
+
+/// Ballots cast on one poll.
+#[derive(Default)]
+struct PollBallots {
+    /// Ballots on attributed polls, by voter.
+    attributed: HashMap<Uuid, Ballot>,
+    /// Who voted on an anonymous poll, kept apart from what they chose.
+    voters: HashSet<Uuid>,
+    anonymous: Vec<Ballot>,
+}
+
+impl PollBallots {
+    fn all(&self) -> impl Iterator<Item = &Ballot> {
+        self.attributed.values().chain(self.anonymous.iter())
+    }
+}
+
+#[derive(Default)]
+struct PollData {
+    polls: HashMap<String, Poll>,
+    ballots: HashMap<String, PollBallots>,
+    /// poll id -> when it was soft-deleted
+    deleted: HashMap<String, DateTime<Utc>>,
+}
+
+/// Keeps polls in a process-local map; everything is lost on restart.
+#[derive(Default)]
+pub struct MemoryPollStore {
+    data: Mutex<PollData>,
+}
+
+impl PollData {
+    /// Copies a poll out with its counts derived from the recorded ballots.
+    fn tallied(&self, poll: &Poll) -> Poll {
+        let mut poll = poll.clone();
+        let ballots = self.ballots.get(&poll.id);
+        let ranked = poll.voting_method.is_ranked();
+        for option in poll.options.iter_mut() {
+            option.votes = ballots.map_or(0, |ballots| {
+                ballots
+                    .all()
+                    .filter(|ballot| {
+                        if ranked {
+                            ballot.option_ids.first() == Some(&option.id)
+                        } else {
+                            ballot.option_ids.contains(&option.id)
+                        }
+                    })
+                    .count() as i32
+            });
+        }
+        poll.refresh_tally();
+        poll
+    }
+}
+
+#[async_trait]
+impl PollStore for MemoryPollStore {
+    async fn insert_poll(&self, poll: &Poll) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        data.polls.insert(poll.id.clone(), poll.clone());
+        Ok(())
+    }
+
+    async fn update_poll(&self, poll: &Poll, allow_votes: bool) -> StoreResult<bool> {
+        let mut data = self.data.lock().await;
+        let has_votes = data
+            .ballots
+            .get(&poll.id)
+            .is_some_and(|ballots| ballots.all().next().is_some());
+        if (has_votes && !allow_votes) || data.deleted.contains_key(&poll.id) {
+            return Ok(false);
+        }
+        let Some(stored) = data.polls.get_mut(&poll.id) else {
+            return Ok(false);
+        };
+        stored.title = poll.title.clone();
+        stored.options = poll.options.clone();
+        stored.max_choices = poll.max_choices;
+        stored.visibility = poll.visibility;
+        stored.share_token = poll.share_token.clone();
+        stored.allowed_users = poll.allowed_users.clone();
+        Ok(true)
+    }
+
+    async fn set_share_token(&self, poll_id: &str, share_token: &str) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        if let Some(poll) = data.polls.get_mut(poll_id) {
+            poll.share_token = Some(share_token.to_string());
+        }
+        Ok(())
+    }
+
+    async fn get_poll(&self, poll_id: &str) -> StoreResult<Option<Poll>> {
+        let data = self.data.lock().await;
+        if data.deleted.contains_key(poll_id) {
+            return Ok(None);
+        }
+        Ok(data.polls.get(poll_id).map(|poll| data.tallied(poll)))
+    }
+
+    async fn list_polls(&self, filter: &PollFilter) -> StoreResult<Vec<Poll>> {
+        let data = self.data.lock().await;
+        let viewer = filter.viewer.as_deref();
+        let mut polls_vec: Vec<Poll> = data
+            .polls
+            .values()
+            .filter(|poll| !data.deleted.contains_key(&poll.id))
+            .map(|poll| data.tallied(poll))
+            .filter(|poll| filter.matches(poll))
+            .filter(|poll| {
+                filter
+                    .after
+                    .as_ref()
+                    .is_none_or(|after| after.precedes(poll, viewer))
+            })
+            .collect();
+        polls_vec.sort_by_cached_key(|poll| (filter.sort.sort_key(poll, viewer), poll.id.clone()));
+        polls_vec.truncate(filter.limit);
+        Ok(polls_vec)
+    }
+
+    async fn cast_ballot(
+        &self,
+        poll_id: &str,
+        voter_id: Uuid,
+        ballot: &Ballot,
+        allow_change: bool,
+    ) -> StoreResult<VoteOutcome> {
+        let mut data = self.data.lock().await;
+        if data.deleted.contains_key(poll_id) {
+            return Ok(VoteOutcome::PollNotFound);
+        }
+        let Some(poll) = data.polls.get(poll_id) else {
+            return Ok(VoteOutcome::PollNotFound);
+        };
+        let privacy = poll.ballot_privacy;
+        let ballots = data.ballots.entry(poll_id.to_string()).or_default();
+
+        if privacy == BallotPrivacy::Anonymous {
+            if !ballots.voters.insert(voter_id) {
+                return Ok(VoteOutcome::AlreadyVoted);
+            }
+            ballots.anonymous.push(ballot.clone());
+            return Ok(VoteOutcome::Recorded);
+        }
+
+        let ballots = &mut ballots.attributed;
+        let outcome = match ballots.get(&voter_id) {
+            None => VoteOutcome::Recorded,
+            Some(previous) if previous == ballot => VoteOutcome::Unchanged,
+            Some(previous) if allow_change => VoteOutcome::Changed {
+                previous_option_ids: previous.option_ids.clone(),
+            },
+            Some(_) => return Ok(VoteOutcome::AlreadyVoted),
+        };
+
+        ballots.insert(voter_id, ballot.clone());
+        Ok(outcome)
+    }
+
+    async fn ballots(&self, poll_id: &str) -> StoreResult<Vec<Ballot>> {
+        let data = self.data.lock().await;
+        Ok(data
+            .ballots
+            .get(poll_id)
+            .map(|ballots| ballots.all().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn attributed_ballots(&self, poll_id: &str) -> StoreResult<Vec<(Uuid, Ballot)>> {
+        let data = self.data.lock().await;
+        Ok(data
+            .ballots
+            .get(poll_id)
+            .map(|ballots| {
+                ballots
+                    .attributed
+                    .iter()
+                    .map(|(voter_id, ballot)| (*voter_id, ballot.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn voted_polls(&self, voter_id: Uuid) -> StoreResult<Vec<(Poll, Option<Ballot>)>> {
+        let data = self.data.lock().await;
+        let mut voted: Vec<(Poll, Option<Ballot>)> = data
+            .polls
+            .values()
+            .filter(|poll| !data.deleted.contains_key(&poll.id))
+            .filter_map(|poll| {
+                let ballots = data.ballots.get(&poll.id)?;
+                if let Some(ballot) = ballots.attributed.get(&voter_id) {
+                    Some((data.tallied(poll), Some(ballot.clone())))
+                } else if ballots.voters.contains(&voter_id) {
+                    Some((data.tallied(poll), None))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        voted.sort_by_key(|(poll, _)| std::cmp::Reverse(poll.created_at));
+        Ok(voted)
+    }
+
+    async fn set_state(&self, poll: &Poll, from: PollState) -> StoreResult<bool> {
+        let mut data = self.data.lock().await;
+        if data.deleted.contains_key(&poll.id) {
+            return Ok(false);
+        }
+        let Some(stored) = data.polls.get_mut(&poll.id) else {
+            return Ok(false);
+        };
+        if stored.state != from {
+            return Ok(false);
+        }
+        stored.state = poll.state;
+        stored.is_closed = poll.is_closed;
+        stored.closed_at = poll.closed_at;
+        stored.closed_by = poll.closed_by.clone();
+        stored.closes_at = poll.closes_at;
+        Ok(true)
+    }
+
+    async fn open_scheduled(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        let mut data = self.data.lock().await;
+        let PollData { polls, deleted, .. } = &mut *data;
+        let mut opened = Vec::new();
+        for poll in polls.values_mut() {
+            if poll.state == PollState::Scheduled
+                && !deleted.contains_key(&poll.id)
+                && !poll.is_pending_at(now)
+            {
+                poll.state = PollState::Open;
+                opened.push(poll.id.clone());
+            }
+        }
+        Ok(opened)
+    }
+
+    async fn close_expired(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        let mut data = self.data.lock().await;
+        let PollData { polls, deleted, .. } = &mut *data;
+        let mut closed = Vec::new();
+        for poll in polls.values_mut() {
+            if matches!(poll.state, PollState::Scheduled | PollState::Open)
+                && !deleted.contains_key(&poll.id)
+                && poll.is_closed_at(now)
+            {
+                let closes_at = poll.closes_at.unwrap_or(now);
+                let _ = poll.transition(PollState::Closed, None, closes_at);
+                closed.push(poll.id.clone());
+            }
+        }
+        Ok(closed)
+    }
+
+    async fn reset_votes(&self, poll_id: &str) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        data.ballots.remove(poll_id);
+        Ok(())
+    }
+
+    async fn soft_delete_poll(&self, poll_id: &str, deleted_at: DateTime<Utc>) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        if data.polls.contains_key(poll_id) {
+            data.deleted
+                .entry(poll_id.to_string())
+                .or_insert(deleted_at);
+        }
+        Ok(())
+    }
+
+    async fn get_deleted_poll(&self, poll_id: &str) -> StoreResult<Option<(Poll, DateTime<Utc>)>> {
+        let data = self.data.lock().await;
+        Ok(data.deleted.get(poll_id).and_then(|deleted_at| {
+            data.polls
+                .get(poll_id)
+                .map(|poll| (data.tallied(poll), *deleted_at))
+        }))
+    }
+
+    async fn restore_poll(&self, poll_id: &str) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        data.deleted.remove(poll_id);
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        let mut data = self.data.lock().await;
+        let expired: Vec<String> = data
+            .deleted
+            .iter()
+            .filter(|(_, deleted_at)| **deleted_at < deleted_before)
+            .map(|(poll_id, _)| poll_id.clone())
+            .collect();
+        for poll_id in &expired {
+            data.deleted.remove(poll_id);
+            data.ballots.remove(poll_id);
+            data.polls.remove(poll_id);
+        }
+        Ok(expired)
+    }
+}
+
+#[derive(Default)]
+struct UserData {
+    name_to_id: HashMap<String, Uuid>,
+    keys: HashMap<Uuid, Vec<Passkey>>,
+}
+
+/// Keeps users and passkeys in process-local maps; everything is lost on restart.
+#[derive(Default)]
+pub struct MemoryUserStore {
+    data: Mutex<UserData>,
+}
+
+#[async_trait]
+impl UserStore for MemoryUserStore {
+    async fn find_user_id(&self, username: &str) -> StoreResult<Option<Uuid>> {
+        let data = self.data.lock().await;
+        Ok(data.name_to_id.get(username).copied())
+    }
+
+    async fn find_username(&self, user_id: Uuid) -> StoreResult<Option<String>> {
+        let data = self.data.lock().await;
+        Ok(data
+            .name_to_id
+            .iter()
+            .find(|(_, &id)| id == user_id)
+            .map(|(name, _)| name.clone()))
+    }
+
+    async fn passkeys(&self, user_id: Uuid) -> StoreResult<Vec<Passkey>> {
+        let data = self.data.lock().await;
+        Ok(data.keys.get(&user_id).cloned().unwrap_or_default())
+    }
+
+    async fn add_passkey(
+        &self,
+        user_id: Uuid,
+        username: &str,
+        passkey: &Passkey,
+    ) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        if data
+            .name_to_id
+            .get(username)
+            .is_some_and(|owner| *owner != user_id)
+        {
+            return Err(StoreError::UsernameTaken(username.to_string()));
+        }
+        data.keys.entry(user_id).or_default().push(passkey.clone());
+        data.name_to_id.insert(username.to_string(), user_id);
+        Ok(())
+    }
+
+    async fn update_passkey(&self, passkey: &Passkey) -> StoreResult<()> {
+        let mut data = self.data.lock().await;
+        for key in data.keys.values_mut().flatten() {
+            if key.cred_id() == passkey.cred_id() {
+                *key = passkey.clone();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+pub mod memory;
+pub mod mysql;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+use crate::models::listing::PollFilter;
+use crate::models::poll::{Poll, PollState, VoteOutcome};
+use crate::tally::Ballot;
+
+pub use memory::{MemoryPollStore, MemoryUserStore};
+pub use mysql::MySqlStore;
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Database Error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Username {0} belongs to another user")]
+    UsernameTaken(String),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Persistence for polls and their votes.
+///
+/// Option vote counts and `total_votes` on returned polls always reflect
+/// the votes recorded so far; on ranked polls only first preferences count.
+#[async_trait]
+pub trait PollStore: Send + Sync {
+    async fn insert_poll(&self, poll: &Poll) -> StoreResult<()>;
+
+    /// Saves a poll's title, options (in order), `max_choices`, visibility,
+    /// share token and allowed users; options no longer listed are removed.
+    /// Without `allow_votes` nothing is saved once the poll has a ballot, and
+    /// `false` is returned.
+    async fn update_poll(&self, poll: &Poll, allow_votes: bool) -> StoreResult<bool>;
+
+    /// Replaces a poll's share token, invalidating the old one.
+    async fn set_share_token(&self, poll_id: &str, share_token: &str) -> StoreResult<()>;
+
+    /// A poll that has not been deleted.
+    async fn get_poll(&self, poll_id: &str) -> StoreResult<Option<Poll>>;
+
+    /// Up to `filter.limit` polls that have not been deleted and match
+    /// `filter`, in its sort order and after its cursor.
+    async fn list_polls(&self, filter: &PollFilter) -> StoreResult<Vec<Poll>>;
+
+    /// Records `voter_id`'s ballot. Each voter holds at most one ballot per
+    /// poll; with `allow_change` an existing ballot is replaced instead of
+    /// being rejected.
+    ///
+    /// On anonymous polls only the fact that `voter_id` voted is kept, never
+    /// linked to the ballot, so such ballots can never be changed. Nothing is
+    /// stored for missing or deleted polls.
+    async fn cast_ballot(
+        &self,
+        poll_id: &str,
+        voter_id: Uuid,
+        ballot: &Ballot,
+        allow_change: bool,
+    ) -> StoreResult<VoteOutcome>;
+
+    /// Every ballot recorded for a poll, each in the order it was cast.
+    async fn ballots(&self, poll_id: &str) -> StoreResult<Vec<Ballot>>;
+
+    /// Ballots with the voter who cast them. Always empty for anonymous polls.
+    async fn attributed_ballots(&self, poll_id: &str) -> StoreResult<Vec<(Uuid, Ballot)>>;
+
+    /// Polls `voter_id` has voted in, newest first, each with their ballot
+    /// unless the poll is anonymous.
+    async fn voted_polls(&self, voter_id: Uuid) -> StoreResult<Vec<(Poll, Option<Ballot>)>>;
+
+    /// Saves `poll`'s `state`, `is_closed`, `closed_at`, `closed_by` and
+    /// `closes_at`, but only if the stored poll is still in state `from`.
+    /// Returns whether it was.
+    async fn set_state(&self, poll: &Poll, from: PollState) -> StoreResult<bool>;
+
+    /// Opens every scheduled poll whose `opens_at` is at or before `now`,
+    /// returning their ids.
+    async fn open_scheduled(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>>;
+
+    /// Closes every scheduled or open poll whose `closes_at` is at or
+    /// before `now`, returning their ids.
+    async fn close_expired(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>>;
+
+    /// Discards every ballot, letting everyone vote again.
+    async fn reset_votes(&self, poll_id: &str) -> StoreResult<()>;
+
+    /// Hides a poll from every other query until it is restored or purged.
+    async fn soft_delete_poll(&self, poll_id: &str, deleted_at: DateTime<Utc>) -> StoreResult<()>;
+
+    /// A soft-deleted poll along with when it was deleted.
+    async fn get_deleted_poll(&self, poll_id: &str) -> StoreResult<Option<(Poll, DateTime<Utc>)>>;
+
+    async fn restore_poll(&self, poll_id: &str) -> StoreResult<()>;
+
+    /// Permanently removes polls deleted before `deleted_before`, returning their ids.
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> StoreResult<Vec<String>>;
+}
+
+/// Persistence for WebAuthn users and their registered passkeys.
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn find_user_id(&self, username: &str) -> StoreResult<Option<Uuid>>;
+
+    async fn find_username(&self, user_id: Uuid) -> StoreResult<Option<String>>;
+
+    async fn passkeys(&self, user_id: Uuid) -> StoreResult<Vec<Passkey>>;
+
+    /// Stores a freshly registered passkey, creating the user on their first
+    /// one. Fails with `StoreError::UsernameTaken` if `username` already
+    /// belongs to a different user id.
+    async fn add_passkey(
+        &self,
+        user_id: Uuid,
+        username: &str,
+        passkey: &Passkey,
+    ) -> StoreResult<()>;
+
+    /// Persists a passkey after `update_credential` bumped its counter or backup state.
+    async fn update_passkey(&self, passkey: &Passkey) -> StoreResult<()>;
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+use super::{PollStore, StoreError, StoreResult, UserStore};
+use crate::db;
+use crate::models::listing::PollFilter;
+use crate::models::poll::{Poll, PollState, VoteOutcome};
+use crate::tally::Ballot;
+
+#[derive(Clone)]
+pub struct MySqlStore {
+    pool: MySqlPool,
+}
+
+impl MySqlStore {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PollStore for MySqlStore {
+    async fn insert_poll(&self, poll: &Poll) -> StoreResult<()> {
+        Ok(db::poll::insert_poll(&self.pool, poll).await?)
+    }
+
+    async fn update_poll(&self, poll: &Poll, allow_votes: bool) -> StoreResult<bool> {
+        Ok(db::poll::update_poll(&self.pool, poll, allow_votes).await?)
+    }
+
+    async fn set_share_token(&self, poll_id: &str, share_token: &str) -> StoreResult<()> {
+        Ok(db::poll::set_share_token(&self.pool, poll_id, share_token).await?)
+    }
+
+    async fn get_poll(&self, poll_id: &str) -> StoreResult<Option<Poll>> {
+        Ok(db::poll::fetch_poll(&self.pool, poll_id).await?)
+    }
+
+    async fn list_polls(&self, filter: &PollFilter) -> StoreResult<Vec<Poll>> {
+        Ok(db::poll::fetch_polls(&self.pool, filter).await?)
+    }
+
+    async fn cast_ballot(
+        &self,
+        poll_id: &str,
+        voter_id: Uuid,
+        ballot: &Ballot,
+        allow_change: bool,
+    ) -> StoreResult<VoteOutcome> {
+        Ok(db::poll::cast_ballot(&self.pool, poll_id, voter_id, ballot, allow_change).await?)
+    }
+
+    async fn ballots(&self, poll_id: &str) -> StoreResult<Vec<Ballot>> {
+        Ok(db::poll::fetch_ballots(&self.pool, poll_id).await?)
+    }
+
+    async fn attributed_ballots(&self, poll_id: &str) -> StoreResult<Vec<(Uuid, Ballot)>> {
+        Ok(db::poll::fetch_attributed_ballots(&self.pool, poll_id).await?)
+    }
+
+    async fn voted_polls(&self, voter_id: Uuid) -> StoreResult<Vec<(Poll, Option<Ballot>)>> {
+        Ok(db::poll::fetch_voted_polls(&self.pool, voter_id).await?)
+    }
+
+    async fn set_state(&self, poll: &Poll, from: PollState) -> StoreResult<bool> {
+        Ok(db::poll::set_state(&self.pool, poll, from).await?)
+    }
+
+    async fn open_scheduled(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        Ok(db::poll::open_scheduled(&self.pool, now).await?)
+    }
+
+    async fn close_expired(&self, now: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        Ok(db::poll::close_expired(&self.pool, now).await?)
+    }
+
+    async fn reset_votes(&self, poll_id: &str) -> StoreResult<()> {
+        Ok(db::poll::delete_votes(&self.pool, poll_id).await?)
+    }
+
+    async fn soft_delete_poll(&self, poll_id: &str, deleted_at: DateTime<Utc>) -> StoreResult<()> {
+        Ok(db::poll::soft_delete_poll(&self.pool, poll_id, deleted_at).await?)
+    }
+
+    async fn get_deleted_poll(&self, poll_id: &str) -> StoreResult<Option<(Poll, DateTime<Utc>)>> {
+        Ok(db::poll::fetch_deleted_poll(&self.pool, poll_id).await?)
+    }
+
+    async fn restore_poll(&self, poll_id: &str) -> StoreResult<()> {
+        Ok(db::poll::restore_poll(&self.pool, poll_id).await?)
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> StoreResult<Vec<String>> {
+        Ok(db::poll::purge_deleted(&self.pool, deleted_before).await?)
+    }
+}
+
+#[async_trait]
+impl UserStore for MySqlStore {
+    async fn find_user_id(&self, username: &str) -> StoreResult<Option<Uuid>> {
+        Ok(db::user::find_user_id(&self.pool, username).await?)
+    }
+
+    async fn find_username(&self, user_id: Uuid) -> StoreResult<Option<String>> {
+        Ok(db::user::find_username(&self.pool, user_id).await?)
+    }
+
+    async fn passkeys(&self, user_id: Uuid) -> StoreResult<Vec<Passkey>> {
+        Ok(db::user::fetch_passkeys(&self.pool, user_id).await?)
+    }
+
+    async fn add_passkey(
+        &self,
+        user_id: Uuid,
+        username: &str,
+        passkey: &Passkey,
+    ) -> StoreResult<()> {
+        if !db::user::insert_credential(&self.pool, user_id, username, passkey).await? {
+            return Err(StoreError::UsernameTaken(username.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn update_passkey(&self, passkey: &Passkey) -> StoreResult<()> {
+        Ok(db::user::update_credential(&self.pool, passkey).await?)
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Ballot, Tally, TallyDetail, TallyMethod};
+
+/// Pairwise working of a Condorcet count. Both matrices are indexed in the
+/// order of `options`; row `i`, column `j` is about `i` against `j`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Condorcet {
+    pub options: Vec<String>,
+    /// Ballots ranking `i` above `j`.
+    pub pairwise: Vec<Vec<u32>>,
+    /// Strength of the strongest beatpath from `i` to `j`.
+    pub strongest_paths: Vec<Vec<u32>>,
+    /// The option beating every other head to head, if there is one.
+    pub condorcet_winner: Option<String>,
+}
+
+/// Counts pairwise preferences from ranked ballots. Ranked options beat
+/// unranked ones; options a ballot leaves unranked tie with each other.
+pub fn pairwise_matrix(options: &[String], ballots: &[Ballot]) -> Vec<Vec<u32>> {
+    let n = options.len();
+    let mut matrix = vec![vec![0; n]; n];
+
+    for ballot in ballots {
+        let rank: Vec<Option<usize>> = options
+            .iter()
+            .map(|id| ballot.option_ids.iter().position(|choice| choice == id))
+            .collect();
+        for i in 0..n {
+            for j in 0..n {
+                let prefers = match (rank[i], rank[j]) {
+                    (Some(a), Some(b)) => a < b,
+                    (Some(_), None) => true,
+                    _ => false,
+                };
+                if prefers {
+                    matrix[i][j] += 1;
+                }
+            }
+        }
+    }
+
+    matrix
+}
+
+/// The Schulze method: options win through their strongest chains of
+/// pairwise victories, which always yields a winner set even without a
+/// Condorcet winner.
+pub struct Schulze;
+
+impl TallyMethod for Schulze {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally {
+        let n = options.len();
+        let pairwise = pairwise_matrix(options, ballots);
+
+        let mut paths = vec![vec![0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                if i != j && pairwise[i][j] > pairwise[j][i] {
+                    paths[i][j] = pairwise[i][j];
+                }
+            }
+        }
+        for k in 0..n {
+            for i in 0..n {
+                for j in 0..n {
+                    if i != j && i != k && j != k {
+                        paths[i][j] = paths[i][j].max(paths[i][k].min(paths[k][j]));
+                    }
+                }
+            }
+        }
+
+        let condorcet_winner = (0..n)
+            .find(|&i| (0..n).all(|j| i == j || pairwise[i][j] > pairwise[j][i]))
+            .map(|i| options[i].clone());
+        let leaders = if ballots.is_empty() {
+            Vec::new()
+        } else {
+            (0..n)
+                .filter(|&i| (0..n).all(|j| i == j || paths[i][j] >= paths[j][i]))
+                .map(|i| options[i].clone())
+                .collect()
+        };
+
+        Tally {
+            leaders,
+            detail: TallyDetail::Condorcet {
+                condorcet: Condorcet {
+                    options: options.to_vec(),
+                    pairwise,
+                    strongest_paths: paths,
+                    condorcet_winner,
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use super::{top, Ballot, OptionTotal, Tally, TallyDetail, TallyMethod};
+
+/// One vote for every option a ballot selects.
+fn totals(options: &[String], ballots: &[Ballot]) -> Vec<OptionTotal> {
+    options
+        .iter()
+        .map(|option_id| OptionTotal {
+            option_id: option_id.clone(),
+            votes: ballots
+                .iter()
+                .filter(|ballot| ballot.option_ids.contains(option_id))
+                .count() as u32,
+        })
+        .collect()
+}
+
+fn leaders(totals: &[OptionTotal]) -> Vec<String> {
+    top(totals.iter().map(|total| (&total.option_id, total.votes)))
+}
+
+/// The most selected option wins.
+pub struct Plurality;
+
+impl TallyMethod for Plurality {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally {
+        let totals = totals(options, ballots);
+        Tally {
+            leaders: leaders(&totals),
+            detail: TallyDetail::Plurality { totals },
+        }
+    }
+}
+
+/// Voters approve any subset; the most approved option wins.
+pub struct Approval;
+
+impl TallyMethod for Approval {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally {
+        let totals = totals(options, ballots);
+        Tally {
+            leaders: leaders(&totals),
+            detail: TallyDetail::Approval { totals },
+        }
+    }
+}
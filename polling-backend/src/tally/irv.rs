@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Ballot, Tally, TallyDetail, TallyMethod};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RoundTally {
+    pub option_id: String,
+    pub votes: u32,
+}
+
+/// Ballots moved off an eliminated option; `to` is `None` when they ran out
+/// of preferences.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    pub from: String,
+    pub to: Option<String>,
+    pub votes: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RunoffRound {
+    pub round: u32,
+    /// Votes held by every option still in the count, in poll order.
+    pub tallies: Vec<RoundTally>,
+    /// Ballots with no continuing option left.
+    pub exhausted: u32,
+    /// Options dropped at the end of this round.
+    pub eliminated: Vec<String>,
+    pub transfers: Vec<Transfer>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Runoff {
+    pub rounds: Vec<RunoffRound>,
+    /// `None` when there are no ballots or the last options tie.
+    pub winner: Option<String>,
+}
+
+/// Counts ranked ballots by instant runoff.
+///
+/// Each round every ballot counts for its highest ranked continuing option.
+/// An option holding a strict majority of the continuing ballots wins;
+/// otherwise every option tied for fewest votes is eliminated and its
+/// ballots move to their next continuing preference. If all remaining
+/// options tie, the count ends without a winner. Ids not in `options` are
+/// ignored.
+pub fn instant_runoff(options: &[String], ballots: &[Ballot]) -> Runoff {
+    let mut continuing: Vec<&String> = options.iter().collect();
+    let mut rounds = Vec::new();
+
+    if ballots.is_empty() || options.is_empty() {
+        return Runoff {
+            rounds,
+            winner: None,
+        };
+    }
+
+    loop {
+        let live: HashSet<&String> = continuing.iter().copied().collect();
+        let top_choice = |ballot: &Ballot| {
+            ballot
+                .option_ids
+                .iter()
+                .find(|id| live.contains(id))
+                .cloned()
+        };
+
+        let mut counts: HashMap<&String, u32> = continuing.iter().map(|id| (*id, 0)).collect();
+        let mut exhausted = 0;
+        for ballot in ballots {
+            match ballot.option_ids.iter().find(|id| live.contains(id)) {
+                Some(id) => *counts.get_mut(id).unwrap() += 1,
+                None => exhausted += 1,
+            }
+        }
+
+        let tallies: Vec<RoundTally> = continuing
+            .iter()
+            .map(|id| RoundTally {
+                option_id: (*id).clone(),
+                votes: counts[id],
+            })
+            .collect();
+        let active = ballots.len() as u32 - exhausted;
+        let most = tallies.iter().map(|t| t.votes).max().unwrap_or(0);
+        let fewest = tallies.iter().map(|t| t.votes).min().unwrap_or(0);
+
+        let majority = tallies
+            .iter()
+            .find(|t| t.votes * 2 > active)
+            .map(|t| t.option_id.clone());
+        let winner = match majority {
+            Some(winner) => Some(winner),
+            None if continuing.len() == 1 => Some(continuing[0].clone()),
+            None => None,
+        };
+        let round = rounds.len() as u32 + 1;
+
+        if winner.is_some() || most == fewest {
+            rounds.push(RunoffRound {
+                round,
+                tallies,
+                exhausted,
+                eliminated: Vec::new(),
+                transfers: Vec::new(),
+            });
+            return Runoff { rounds, winner };
+        }
+
+        let eliminated: Vec<String> = tallies
+            .iter()
+            .filter(|t| t.votes == fewest)
+            .map(|t| t.option_id.clone())
+            .collect();
+        continuing.retain(|id| !eliminated.contains(id));
+
+        let remaining: HashSet<&String> = continuing.iter().copied().collect();
+        let mut moved: Vec<Transfer> = Vec::new();
+        for ballot in ballots {
+            let Some(from) = top_choice(ballot) else {
+                continue;
+            };
+            if !eliminated.contains(&from) {
+                continue;
+            }
+            let to = ballot
+                .option_ids
+                .iter()
+                .find(|id| remaining.contains(id))
+                .cloned();
+            match moved.iter_mut().find(|t| t.from == from && t.to == to) {
+                Some(transfer) => transfer.votes += 1,
+                None => moved.push(Transfer { from, to, votes: 1 }),
+            }
+        }
+
+        rounds.push(RunoffRound {
+            round,
+            tallies,
+            exhausted,
+            eliminated,
+            transfers: moved,
+        });
+    }
+}
+
+/// Instant runoff as a `TallyMethod`. When the last options tie they all lead.
+pub struct InstantRunoff;
+
+impl TallyMethod for InstantRunoff {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally {
+        let runoff = instant_runoff(options, ballots);
+        let leaders = match (&runoff.winner, runoff.rounds.last()) {
+            (Some(winner), _) => vec![winner.clone()],
+            (None, Some(last)) => last
+                .tallies
+                .iter()
+                .filter(|tally| tally.votes > 0)
+                .map(|tally| tally.option_id.clone())
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+
+        Tally {
+            leaders,
+            detail: TallyDetail::RankedChoice { runoff },
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Pure counting functions over stored ballots. Nothing here touches
+//! storage or the clock, so every method can be recomputed at will.
+
+pub mod condorcet;
+pub mod counting;
+pub mod irv;
+pub mod score;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::poll::VotingMethod;
+
+/// One voter's ballot.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Ballot {
+    /// Selected options, most preferred first on ranked polls.
+    pub option_ids: Vec<String>,
+    /// Points given to each selected option on score polls.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub scores: HashMap<String, u32>,
+}
+
+impl Ballot {
+    pub fn new(option_ids: Vec<String>) -> Self {
+        Self {
+            option_ids,
+            scores: HashMap::new(),
+        }
+    }
+
+    /// A score ballot; `option_ids` follows the order of `scores`.
+    pub fn scored(scores: Vec<(String, u32)>) -> Self {
+        Self {
+            option_ids: scores.iter().map(|(id, _)| id.clone()).collect(),
+            scores: scores.into_iter().collect(),
+        }
+    }
+}
+
+/// Number of ballots selecting an option.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct OptionTotal {
+    pub option_id: String,
+    pub votes: u32,
+}
+
+/// Outcome of counting a poll's ballots with its voting method.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Tally {
+    /// Options sharing the best outcome; empty when nothing was counted.
+    pub leaders: Vec<String>,
+    #[serde(flatten)]
+    pub detail: TallyDetail,
+}
+
+impl Tally {
+    /// The single leader, if there is no tie.
+    pub fn winner(&self) -> Option<&String> {
+        match self.leaders.as_slice() {
+            [winner] => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// How far each counted option got by this method, in poll order; a
+    /// higher value is a better finish and equal values tie.
+    ///
+    /// Runoff options are ordered by the last round they reached, then by
+    /// their votes in it. Condorcet options are ordered by how many others
+    /// they beat on strongest paths.
+    pub fn standing(&self) -> Vec<(String, u64)> {
+        match &self.detail {
+            TallyDetail::Plurality { totals } | TallyDetail::Approval { totals } => totals
+                .iter()
+                .map(|total| (total.option_id.clone(), total.votes as u64))
+                .collect(),
+            TallyDetail::Score { scores } => scores
+                .iter()
+                .map(|score| (score.option_id.clone(), score.total as u64))
+                .collect(),
+            TallyDetail::RankedChoice { runoff } => {
+                let mut standing: Vec<(String, u64)> = Vec::new();
+                for round in &runoff.rounds {
+                    for tally in &round.tallies {
+                        let reached = (round.round as u64) << 32 | tally.votes as u64;
+                        match standing.iter_mut().find(|(id, _)| *id == tally.option_id) {
+                            Some(entry) => entry.1 = reached,
+                            None => standing.push((tally.option_id.clone(), reached)),
+                        }
+                    }
+                }
+                standing
+            }
+            TallyDetail::Condorcet { condorcet } => {
+                let paths = &condorcet.strongest_paths;
+                condorcet
+                    .options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option_id)| {
+                        let beaten = (0..paths.len())
+                            .filter(|&j| paths[i][j] > paths[j][i])
+                            .count();
+                        (option_id.clone(), beaten as u64)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Method-specific working shown alongside the leaders.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum TallyDetail {
+    Plurality { totals: Vec<OptionTotal> },
+    Approval { totals: Vec<OptionTotal> },
+    Score { scores: Vec<score::ScoreTotal> },
+    RankedChoice { runoff: irv::Runoff },
+    Condorcet { condorcet: condorcet::Condorcet },
+}
+
+/// A way of turning ballots into a `Tally`.
+///
+/// `options` lists every option id in poll order; ballot entries naming
+/// other ids are ignored.
+pub trait TallyMethod: Send + Sync {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally;
+}
+
+/// The engine that counts polls using `method`.
+pub fn engine(method: VotingMethod) -> &'static dyn TallyMethod {
+    match method {
+        VotingMethod::Plurality => &counting::Plurality,
+        VotingMethod::Approval => &counting::Approval,
+        VotingMethod::Score => &score::Score,
+        VotingMethod::RankedChoice => &irv::InstantRunoff,
+        VotingMethod::Condorcet => &condorcet::Schulze,
+    }
+}
+
+/// Ids of the entries sharing the highest value, unless nothing scored.
+fn top<'a>(entries: impl Iterator<Item = (&'a String, u32)> + Clone) -> Vec<String> {
+    let best = entries.clone().map(|(_, value)| value).max().unwrap_or(0);
+    if best == 0 {
+        return Vec::new();
+    }
+    entries
+        .filter(|(_, value)| *value == best)
+        .map(|(id, _)| id.clone())
+        .collect()
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::{top, Ballot, Tally, TallyDetail, TallyMethod};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ScoreTotal {
+    pub option_id: String,
+    pub total: u32,
+    /// Mean over every ballot, counting unscored options as zero.
+    pub average: f64,
+}
+
+/// Range voting: the option with the highest total score wins.
+pub struct Score;
+
+impl TallyMethod for Score {
+    fn count(&self, options: &[String], ballots: &[Ballot]) -> Tally {
+        let scores: Vec<ScoreTotal> = options
+            .iter()
+            .map(|option_id| {
+                let total: u32 = ballots
+                    .iter()
+                    .filter_map(|ballot| ballot.scores.get(option_id))
+                    .sum();
+                let average = if ballots.is_empty() {
+                    0.0
+                } else {
+                    (total as f64 * 100.0 / ballots.len() as f64).round() / 100.0
+                };
+                ScoreTotal {
+                    option_id: option_id.clone(),
+                    total,
+                    average,
+                }
+            })
+            .collect();
+
+        Tally {
+            leaders: top(scores.iter().map(|score| (&score.option_id, score.total))),
+            detail: TallyDetail::Score { scores },
+        }
+    }
+}
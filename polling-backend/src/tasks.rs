@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::config::poll_undo_window;
+use crate::models::poll::PollEvent;
+use crate::state::AppState;
+
+/// Opens scheduled polls once their `opens_at` passes, closes them once
+/// their `closes_at` passes, and broadcasts each change.
+pub async fn advance_poll_schedules(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+
+    loop {
+        interval.tick().await;
+        open_scheduled_now(&state).await;
+        close_expired_now(&state).await;
+    }
+}
+
+/// Opening pass of `advance_poll_schedules`.
+pub async fn open_scheduled_now(state: &AppState) {
+    match state.polls.open_scheduled(Utc::now()).await {
+        Ok(opened) => broadcast_changed(state, opened, "Opened scheduled").await,
+        Err(e) => tracing::error!("opening scheduled polls -> {:?}", e),
+    }
+}
+
+/// Closing pass of `advance_poll_schedules`.
+pub async fn close_expired_now(state: &AppState) {
+    match state.polls.close_expired(Utc::now()).await {
+        Ok(closed) => broadcast_changed(state, closed, "Closed expired").await,
+        Err(e) => tracing::error!("closing expired polls -> {:?}", e),
+    }
+}
+
+async fn broadcast_changed(state: &AppState, poll_ids: Vec<String>, what: &str) {
+    for poll_id in poll_ids {
+        match state.polls.get_poll(&poll_id).await {
+            Ok(Some(poll)) => {
+                tracing::info!("{} poll {}", what, poll_id);
+                let _ = state.poll_updates.send(PollEvent::Updated(Box::new(poll)));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("loading poll {} -> {:?}", poll_id, e),
+        }
+    }
+}
+
+/// Permanently removes soft-deleted polls once their undo window has passed.
+pub async fn purge_deleted_polls(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+
+        let cutoff = Utc::now() - poll_undo_window();
+        match state.polls.purge_deleted(cutoff).await {
+            Ok(purged) if !purged.is_empty() => {
+                tracing::info!("Purged {} deleted polls", purged.len());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("purging deleted polls -> {:?}", e),
+        }
+    }
+}
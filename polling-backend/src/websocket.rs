@@ -1,126 +1,816 @@
+use axum::extract::ws::{Message, WebSocket};
 use axum::extract::{Path, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt};
+use http::{header::ORIGIN, HeaderMap};
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::WebSocketStream;
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{self, error::SendTimeoutError};
+use tokio::sync::Mutex;
+use tower_sessions::Session;
 
-use crate::{models::poll::Poll, state::AppState};
+use uuid::Uuid;
 
+use crate::{
+    config::frontend_origins,
+    error::ApiError,
+    extract::ApiQuery,
+    handlers::poll::{cast_vote, poll_results},
+    models::listing::{PollFilter, MAX_PAGE_SIZE},
+    models::poll::{Poll, PollAccess, PollEvent, ShareTokenQuery, VoteRequest},
+    models::results::PollResults,
+    state::AppState,
+};
+
+/// Largest frame a client may send; commands are small.
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Messages that may wait to be written to one socket.
+pub const SEND_QUEUE_SIZE: usize = 64;
+
+/// How long a socket's queue may stay full before the client is dropped.
+pub const SLOW_CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything sent over a poll websocket, in either direction. Clients may
+/// add a `request_id` to any command; the acknowledgement or `Error` it
+/// causes echoes it back.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsMessage {
-    Subscribe { poll_id: String },
-    Vote { poll_id: String, option_id: String },
-    PollUpdate { poll: Poll },
+    Subscribe {
+        poll_id: String,
+        /// Share token of a private poll.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    Unsubscribe {
+        poll_id: String,
+    },
+    /// Follow every poll the connection may see, not just subscribed ones.
+    SubscribeAll,
+    UnsubscribeAll,
+    /// Ask for a poll's results; with `live`, keep sending them as votes come in.
+    SubscribeResults {
+        poll_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        #[serde(default)]
+        live: bool,
+    },
+    Vote {
+        poll_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        #[serde(flatten)]
+        choice: VoteRequest,
+    },
+    /// Acknowledges `Subscribe` (with its poll) or `SubscribeAll` (without).
+    Subscribed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        poll_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+    /// Acknowledges `Unsubscribe` (with its poll) or `UnsubscribeAll` (without).
+    Unsubscribed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        poll_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+    /// Acknowledges an accepted `Vote`.
+    Voted {
+        poll_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+    /// A frame or command the server refused. `code` matches the HTTP API's
+    /// error codes, plus the protocol's own `invalid_json`,
+    /// `invalid_message`, `unsupported_message` and `binary_not_supported`.
+    Error {
+        code: String,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+    PollUpdate {
+        poll: Poll,
+    },
+    PollDeleted {
+        poll_id: String,
+    },
+    /// Sent instead of the updates a connection fell too far behind to
+    /// receive: the current state of every poll it follows (the first page
+    /// of them when following all polls), and which of them are gone or no
+    /// longer open to it.
+    Resync {
+        polls: Vec<Poll>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        deleted: Vec<String>,
+    },
+    Results {
+        results: PollResults,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+}
+
+impl WsMessage {
+    /// The message a subscriber with `access` receives for a broadcast
+    /// event, if they may see that poll at all.
+    pub fn from_event(event: PollEvent, access: &PollAccess) -> Option<Self> {
+        match event {
+            PollEvent::Updated(poll) | PollEvent::Deleted(poll)
+                if !poll.is_accessible_by(access) =>
+            {
+                None
+            }
+            PollEvent::Updated(poll) => Some(WsMessage::PollUpdate {
+                poll: poll.view_for(access.username.as_deref()),
+            }),
+            PollEvent::Deleted(poll) => Some(WsMessage::PollDeleted { poll_id: poll.id }),
+        }
+    }
+
+    /// Whether clients may send this message; the rest only flow from the
+    /// server.
+    pub fn is_command(&self) -> bool {
+        matches!(
+            self,
+            WsMessage::Subscribe { .. }
+                | WsMessage::Unsubscribe { .. }
+                | WsMessage::SubscribeAll
+                | WsMessage::UnsubscribeAll
+                | WsMessage::SubscribeResults { .. }
+                | WsMessage::Vote { .. }
+        )
+    }
+}
+
+/// A command read from a client frame, with the `request_id` to echo.
+#[derive(Debug)]
+pub struct ClientCommand {
+    pub message: WsMessage,
+    pub request_id: Option<String>,
 }
 
-pub async fn start_ws_server(state: AppState) {
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3003));
-    let listener = TcpListener::bind(&addr).await.expect("Failed to bind");
-    tracing::info!("WebSocket server listening on: ws://{}", addr);
+impl ClientCommand {
+    pub fn new(message: WsMessage) -> Self {
+        Self {
+            message,
+            request_id: None,
+        }
+    }
 
-    while let Ok((stream, _)) = listener.accept().await {
-        let state = state.clone();
-        tokio::spawn(async move {
-            if let Err(e) = handle_connection(stream, state).await {
-                eprintln!("Error processing connection: {}", e);
+    /// Parses a text frame. Whatever the input, this returns either a
+    /// command or the error to send back.
+    pub fn parse(frame: &str) -> Result<Self, CommandError> {
+        let value: Value = serde_json::from_str(frame)
+            .map_err(|e| CommandError::new("invalid_json", e.to_string()))?;
+        let request_id = value
+            .get("request_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let message: WsMessage = match serde_json::from_value(value) {
+            Ok(message) => message,
+            Err(e) => {
+                return Err(
+                    CommandError::new("invalid_message", e.to_string()).with_request_id(request_id)
+                )
             }
-        });
+        };
+        if !message.is_command() {
+            return Err(CommandError::unsupported().with_request_id(request_id));
+        }
+        Ok(Self {
+            message,
+            request_id,
+        })
     }
 }
 
-pub async fn handle_connection(
-    stream: TcpStream,
+/// Why a client frame was refused; sent back as `WsMessage::Error`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandError {
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+impl CommandError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+            request_id: None,
+        }
+    }
+
+    fn unsupported() -> Self {
+        Self::new(
+            "unsupported_message",
+            "Only commands can be sent to the server",
+        )
+    }
+
+    pub fn with_request_id(self, request_id: Option<String>) -> Self {
+        Self { request_id, ..self }
+    }
+}
+
+impl From<ApiError> for CommandError {
+    fn from(e: ApiError) -> Self {
+        Self::new(e.code(), e.public_message())
+    }
+}
+
+impl From<CommandError> for WsMessage {
+    fn from(e: CommandError) -> Self {
+        WsMessage::Error {
+            code: e.code,
+            message: e.message,
+            request_id: e.request_id,
+        }
+    }
+}
+
+/// The logged-in user behind a socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WsUser {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+impl WsUser {
+    pub async fn from_session(session: &Session) -> Result<Option<Self>, ApiError> {
+        let user_id: Option<Uuid> = session.get("user_id").await?;
+        let username: Option<String> = session.get("username").await?;
+        Ok(user_id
+            .zip(username)
+            .map(|(user_id, username)| Self { user_id, username }))
+    }
+}
+
+/// What one connection listens to: the polls it subscribed to, with the
+/// share token each was opened with, and optionally every poll.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    /// Username of the connection, if logged in.
+    viewer: Option<String>,
+    all: bool,
+    polls: HashMap<String, Option<String>>,
+    /// Polls whose results are pushed with every update.
+    results: HashSet<String>,
+    /// Polls sent to the connection while following every poll, so it can
+    /// be told when one is no longer open to it.
+    seen: HashSet<String>,
+}
+
+impl Subscriptions {
+    pub fn new(viewer: Option<String>) -> Self {
+        Self {
+            viewer,
+            ..Self::default()
+        }
+    }
+
+    pub fn subscribe(&mut self, poll_id: String, token: Option<String>) {
+        self.polls.insert(poll_id, token);
+    }
+
+    /// Subscribes to `poll_id` and to its results.
+    pub fn subscribe_results(&mut self, poll_id: String, token: Option<String>) {
+        self.results.insert(poll_id.clone());
+        self.subscribe(poll_id, token);
+    }
+
+    pub fn unsubscribe(&mut self, poll_id: &str) {
+        self.polls.remove(poll_id);
+        self.results.remove(poll_id);
+        self.seen.remove(poll_id);
+    }
+
+    /// Follow or stop following every poll; single subscriptions are kept.
+    pub fn set_all(&mut self, all: bool) {
+        self.all = all;
+        if !all {
+            self.seen.clear();
+        }
+    }
+
+    /// Records that `poll_id` was sent to the connection outside `forward`.
+    pub fn mark_seen(&mut self, poll_id: String) {
+        if self.all {
+            self.seen.insert(poll_id);
+        }
+    }
+
+    /// Ids of the polls sent while following every poll and not also
+    /// subscribed to by id.
+    pub fn seen(&self) -> impl Iterator<Item = &str> {
+        self.seen
+            .iter()
+            .filter(|poll_id| !self.polls.contains_key(*poll_id))
+            .map(String::as_str)
+    }
+
+    pub fn viewer(&self) -> Option<&str> {
+        self.viewer.as_deref()
+    }
+
+    pub fn follows_results(&self, poll_id: &str) -> bool {
+        self.results.contains(poll_id)
+    }
+
+    pub fn follows_all(&self) -> bool {
+        self.all
+    }
+
+    /// Ids of the polls subscribed to one by one.
+    pub fn polls(&self) -> impl Iterator<Item = &str> {
+        self.polls.keys().map(String::as_str)
+    }
+
+    pub fn is_subscribed(&self, poll_id: &str) -> bool {
+        self.all || self.polls.contains_key(poll_id)
+    }
+
+    /// How the connection may open `poll_id`: its user, plus `token` or
+    /// else the share token presented when subscribing.
+    pub fn access(&self, poll_id: &str, token: Option<String>) -> PollAccess {
+        let token = token.or_else(|| self.polls.get(poll_id).cloned().flatten());
+        PollAccess::new(self.viewer.clone(), token)
+    }
+
+    /// The message to forward for a broadcast event, if the connection
+    /// follows that poll and may see it. A poll the connection was following
+    /// or had been sent but can no longer open (made private or its token
+    /// rotated, say) is dropped from the subscriptions and reported as
+    /// deleted.
+    pub fn forward(&mut self, event: PollEvent) -> Option<WsMessage> {
+        let poll_id = event.poll_id().to_string();
+        if !self.is_subscribed(&poll_id) {
+            return None;
+        }
+        let access = self.access(&poll_id, None);
+        if let PollEvent::Updated(poll) = &event {
+            if !poll.is_accessible_by(&access) {
+                let known = self.polls.contains_key(&poll_id) || self.seen.contains(&poll_id);
+                if !known {
+                    return None;
+                }
+                self.unsubscribe(&poll_id);
+                return Some(WsMessage::PollDeleted { poll_id });
+            }
+        }
+
+        let message = WsMessage::from_event(event, &access)?;
+        if matches!(message, WsMessage::PollDeleted { .. }) {
+            self.seen.remove(&poll_id);
+        } else {
+            self.mark_seen(poll_id);
+        }
+        Some(message)
+    }
+}
+
+/// Bounded queue of messages waiting to be written to one socket, so a
+/// client that reads slowly can't hold up anyone else.
+#[derive(Clone, Debug)]
+pub struct Outbox {
+    queue: mpsc::Sender<WsMessage>,
+    patience: Duration,
+}
+
+impl Outbox {
+    /// An outbox holding up to `capacity` messages, and the receiving end
+    /// for the task writing them out.
+    pub fn new(capacity: usize, patience: Duration) -> (Self, mpsc::Receiver<WsMessage>) {
+        let (queue, queued) = mpsc::channel(capacity);
+        (Self { queue, patience }, queued)
+    }
+
+    /// Queues `message`, waiting while the queue is full. Returns false once
+    /// the queue has stayed full for longer than the patience, or the
+    /// connection is gone; either way it should be dropped.
+    pub async fn send(&self, message: WsMessage) -> bool {
+        match self.queue.send_timeout(message, self.patience).await {
+            Ok(()) => true,
+            Err(SendTimeoutError::Timeout(_)) => {
+                tracing::warn!("dropping websocket client that stopped reading");
+                false
+            }
+            Err(SendTimeoutError::Closed(_)) => false,
+        }
+    }
+}
+
+/// Browsers don't apply CORS to websocket handshakes, so only accept
+/// upgrades from the frontend. Clients that send no `Origin` are not
+/// browsers and can't ride on a user's cookie.
+fn check_origin(headers: &HeaderMap) -> Result<(), ApiError> {
+    match headers.get(ORIGIN) {
+        Some(origin) if !frontend_origins().contains(origin) => Err(ApiError::Forbidden),
+        _ => Ok(()),
+    }
+}
+
+/// Opens a socket speaking the full `WsMessage` protocol, acting as the
+/// session's user if logged in.
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    check_origin(&headers)?;
+    let user = WsUser::from_session(&session).await?;
+
+    Ok(ws
+        .max_message_size(MAX_MESSAGE_SIZE)
+        .on_upgrade(|socket| handle_socket(socket, state, user, None)))
+}
+
+/// Like `websocket_handler`, already subscribed to one poll. Private polls
+/// need the share token (or an allowed session) before the upgrade is
+/// accepted.
+pub async fn poll_websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Path(poll_id): Path<String>,
+    ApiQuery(token): ApiQuery<ShareTokenQuery>,
+    headers: HeaderMap,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    check_origin(&headers)?;
+    let user = WsUser::from_session(&session).await?;
+    let access = PollAccess::new(user.as_ref().map(|user| user.username.clone()), token.token);
+    accessible_poll(&state, &poll_id, &access).await?;
+
+    let subscribe = ClientCommand::new(WsMessage::Subscribe {
+        poll_id,
+        token: access.share_token,
+    });
+    Ok(ws
+        .max_message_size(MAX_MESSAGE_SIZE)
+        .on_upgrade(|socket| handle_socket(socket, state, user, Some(subscribe))))
+}
+
+async fn handle_socket(
+    socket: WebSocket,
     state: AppState,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let ws_stream = accept_async(stream).await?;
-    let (write, mut read) = ws_stream.split();
-    let write = std::sync::Arc::new(tokio::sync::Mutex::new(write));
+    user: Option<WsUser>,
+    first: Option<ClientCommand>,
+) {
+    let (mut write, mut read) = socket.split();
+    let (outbox, mut queued) = Outbox::new(SEND_QUEUE_SIZE, SLOW_CLIENT_TIMEOUT);
+    let viewer = user.as_ref().map(|user| user.username.clone());
+    let subscriptions = Arc::new(Mutex::new(Subscriptions::new(viewer)));
     let mut poll_updates_rx = state.poll_updates.subscribe();
 
+    // Writes queued messages out as fast as the client reads them
+    let mut write_task = tokio::spawn(async move {
+        while let Some(message) = queued.recv().await {
+            if !send_message(&mut write, &message).await {
+                break;
+            }
+        }
+    });
+
+    if let Some(command) = first {
+        handle_command(command, &state, user.as_ref(), &subscriptions, &outbox).await;
+    }
+
     // Spawn a task to handle receiving messages from the client
     let state_clone = state.clone();
-    let write_clone = write.clone();
-    tokio::spawn(async move {
+    let outbox_clone = outbox.clone();
+    let subscriptions_clone = subscriptions.clone();
+    let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = read.next().await {
-            if let Message::Text(text) = msg {
-                let ws_msg: WsMessage = serde_json::from_str(&text).unwrap();
-                let mut write_guard = write_clone.lock().await;
-                handle_ws_message(ws_msg, &state_clone, &mut write_guard).await;
+            let command = match msg {
+                Message::Text(text) => ClientCommand::parse(&text),
+                Message::Binary(_) => Err(CommandError::new(
+                    "binary_not_supported",
+                    "Commands must be sent as JSON text frames",
+                )),
+                // axum answers the close frame; stop reading
+                Message::Close(_) => break,
+                Message::Ping(_) | Message::Pong(_) => continue,
+            };
+            match command {
+                Ok(command) => {
+                    handle_command(
+                        command,
+                        &state_clone,
+                        user.as_ref(),
+                        &subscriptions_clone,
+                        &outbox_clone,
+                    )
+                    .await
+                }
+                Err(e) => {
+                    tracing::info!("websocket frame refused -> {:?}", e);
+                    outbox_clone.send(WsMessage::from(e)).await;
+                }
             }
         }
     });
 
-    // sending poll updates to the client
-    while let Ok((_poll_id, poll)) = poll_updates_rx.recv().await {
-        let update = WsMessage::PollUpdate { poll };
-        if let Ok(msg) = serde_json::to_string(&update) {
-            let _ = write.lock().await.send(Message::Text(msg.into())).await;
+    // Queues the subscribed polls' updates for the client
+    let mut send_task = tokio::spawn(async move {
+        loop {
+            let event = match poll_updates_rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("websocket missed {} poll updates, resyncing", missed);
+                    let messages = match resync(&state, &subscriptions).await {
+                        Ok(messages) => messages,
+                        Err(e) => {
+                            tracing::error!("websocket resync failed -> {:?}", e);
+                            return;
+                        }
+                    };
+                    for message in messages {
+                        if !outbox.send(message).await {
+                            return;
+                        }
+                    }
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            let (update, live_results) = {
+                let mut subscriptions = subscriptions.lock().await;
+                let live_poll = match &event {
+                    PollEvent::Updated(poll) if subscriptions.follows_results(&poll.id) => {
+                        Some(poll.clone())
+                    }
+                    _ => None,
+                };
+                let update = subscriptions.forward(event);
+                // Still followed unless `forward` just revoked it
+                let live_results = live_poll
+                    .filter(|poll| subscriptions.follows_results(&poll.id))
+                    .map(|poll| (poll, subscriptions.viewer().map(str::to_string)));
+                (update, live_results)
+            };
+            let Some(update) = update else {
+                continue;
+            };
+            if !outbox.send(update).await {
+                return;
+            }
+            if let Some((poll, viewer)) = live_results {
+                if let Ok(results) = poll_results(&state, &poll, viewer.as_deref()).await {
+                    let results = WsMessage::Results {
+                        results,
+                        request_id: None,
+                    };
+                    if !outbox.send(results).await {
+                        return;
+                    }
+                }
+            }
         }
-    }
+    });
 
-    Ok(())
+    // Whichever task stops first closes the connection
+    tokio::select! {
+        _ = &mut recv_task => {}
+        _ = &mut send_task => {}
+        _ = &mut write_task => {}
+    }
+    recv_task.abort();
+    send_task.abort();
+    write_task.abort();
 }
 
-pub async fn handle_ws_message(
-    message: WsMessage,
+/// Catches up a connection that missed updates: a `Resync` of the polls it
+/// follows or was sent, then fresh results for those whose results it
+/// follows. Polls it can no longer open are reported deleted and
+/// unsubscribed, as in `Subscriptions::forward`.
+async fn resync(
     state: &AppState,
-    write: &mut SplitSink<WebSocketStream<TcpStream>, Message>,
-) {
-    match message {
-        WsMessage::Subscribe { poll_id } => {
-            if let Some(poll) = state.polls.lock().await.get(&poll_id) {
-                let update = WsMessage::PollUpdate { poll: poll.clone() };
-                if let Ok(msg) = serde_json::to_string(&update) {
-                    let _ = write.send(Message::Text(msg.into())).await;
+    subscriptions: &Mutex<Subscriptions>,
+) -> Result<Vec<WsMessage>, ApiError> {
+    let (viewer, all, followed, seen) = {
+        let subscriptions = subscriptions.lock().await;
+        let followed: Vec<_> = subscriptions
+            .polls()
+            .map(|poll_id| {
+                (
+                    poll_id.to_string(),
+                    subscriptions.access(poll_id, None),
+                    subscriptions.follows_results(poll_id),
+                )
+            })
+            .collect();
+        let seen: Vec<String> = subscriptions.seen().map(str::to_string).collect();
+        let viewer = subscriptions.viewer().map(str::to_string);
+        (viewer, subscriptions.follows_all(), followed, seen)
+    };
+
+    let mut polls = Vec::new();
+    let mut deleted = Vec::new();
+    let mut revoked = Vec::new();
+    let mut results = Vec::new();
+    for (poll_id, access, live) in followed {
+        match state.polls.get_poll(&poll_id).await? {
+            Some(poll) if poll.is_accessible_by(&access) => {
+                if live {
+                    if let Ok(tally) = poll_results(state, &poll, viewer.as_deref()).await {
+                        results.push(WsMessage::Results {
+                            results: tally,
+                            request_id: None,
+                        });
+                    }
                 }
+                polls.push(poll.view_for(viewer.as_deref()));
             }
+            Some(_) => revoked.push(poll_id),
+            None => deleted.push(poll_id),
         }
-        WsMessage::Vote { poll_id, option_id } => {
-            let mut polls = state.polls.lock().await;
-            if let Some(poll) = polls.get_mut(&poll_id) {
-                if let Some(option) = poll.options.iter_mut().find(|opt| opt.id == option_id) {
-                    option.votes += 1;
-                    let _ = state.poll_updates.send((poll_id, poll.clone()));
+    }
+    if all {
+        let filter = PollFilter {
+            viewer: viewer.clone(),
+            limit: MAX_PAGE_SIZE,
+            ..PollFilter::default()
+        };
+        for poll in state.polls.list_polls(&filter).await? {
+            if !polls.iter().any(|known| known.id == poll.id) {
+                polls.push(poll.view_for(viewer.as_deref()));
+            }
+        }
+        // Polls sent earlier but past the first page are checked one by one
+        let access = PollAccess::new(viewer.clone(), None);
+        for poll_id in seen {
+            if polls.iter().any(|known| known.id == poll_id) {
+                continue;
+            }
+            match state.polls.get_poll(&poll_id).await? {
+                Some(poll) if poll.is_accessible_by(&access) => {
+                    polls.push(poll.view_for(viewer.as_deref()));
                 }
+                Some(_) => revoked.push(poll_id),
+                None => deleted.push(poll_id),
             }
         }
-        _ => {
-            tracing::info!(
-                "I don't want to handle this, Unknown message: {:?}",
-                message
-            );
+    }
+    {
+        let mut subscriptions = subscriptions.lock().await;
+        for poll_id in &revoked {
+            subscriptions.unsubscribe(poll_id);
+        }
+        for poll_id in &deleted {
+            subscriptions.seen.remove(poll_id);
+        }
+        for poll in &polls {
+            subscriptions.mark_seen(poll.id.clone());
         }
     }
+    deleted.extend(revoked);
+
+    let mut messages = vec![WsMessage::Resync { polls, deleted }];
+    messages.extend(results);
+    Ok(messages)
 }
 
-pub async fn poll_websocket_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<AppState>,
-    Path(poll_id): Path<String>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state, poll_id))
+async fn accessible_poll(
+    state: &AppState,
+    poll_id: &str,
+    access: &PollAccess,
+) -> Result<Poll, ApiError> {
+    state
+        .polls
+        .get_poll(poll_id)
+        .await?
+        .filter(|poll| poll.is_accessible_by(access))
+        .ok_or_else(|| ApiError::PollNotFound(poll_id.to_string()))
 }
 
-async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState, poll_id: String) {
-    let (mut sender, _receiver) = socket.split();
-    let mut rx = state.poll_updates.subscribe();
+/// Runs one client command, replying with its acknowledgement or an `Error`.
+pub async fn handle_command(
+    command: ClientCommand,
+    state: &AppState,
+    user: Option<&WsUser>,
+    subscriptions: &Mutex<Subscriptions>,
+    outbox: &Outbox,
+) {
+    let request_id = command.request_id.clone();
+    if let Err(e) = run_command(command, state, user, subscriptions, outbox).await {
+        tracing::info!("websocket command refused -> {:?}", e);
+        outbox
+            .send(WsMessage::from(e.with_request_id(request_id)))
+            .await;
+    }
+}
 
-    while let Ok((updated_poll_id, poll)) = rx.recv().await {
-        if updated_poll_id == poll_id {
-            if let Ok(msg) = serde_json::to_string(&poll) {
-                if sender
-                    .send(axum::extract::ws::Message::Text(msg.into()))
+async fn run_command(
+    command: ClientCommand,
+    state: &AppState,
+    user: Option<&WsUser>,
+    subscriptions: &Mutex<Subscriptions>,
+    outbox: &Outbox,
+) -> Result<(), CommandError> {
+    let ClientCommand {
+        message,
+        request_id,
+    } = command;
+    let username = user.map(|user| user.username.clone());
+    match message {
+        WsMessage::Subscribe { poll_id, token } => {
+            let access = PollAccess::new(username.clone(), token.clone());
+            let poll = accessible_poll(state, &poll_id, &access).await?;
+            subscriptions.lock().await.subscribe(poll_id.clone(), token);
+            let ack = WsMessage::Subscribed {
+                poll_id: Some(poll_id),
+                request_id,
+            };
+            outbox.send(ack).await;
+            let update = WsMessage::PollUpdate {
+                poll: poll.view_for(username.as_deref()),
+            };
+            outbox.send(update).await;
+        }
+        WsMessage::SubscribeResults {
+            poll_id,
+            token,
+            live,
+        } => {
+            let access = PollAccess::new(username.clone(), token.clone());
+            let poll = accessible_poll(state, &poll_id, &access).await?;
+            let results = poll_results(state, &poll, username.as_deref()).await?;
+            if live {
+                subscriptions
+                    .lock()
                     .await
-                    .is_err()
-                {
-                    break;
-                }
+                    .subscribe_results(poll_id.clone(), token);
+                let ack = WsMessage::Subscribed {
+                    poll_id: Some(poll_id),
+                    request_id: request_id.clone(),
+                };
+                outbox.send(ack).await;
             }
+            outbox
+                .send(WsMessage::Results {
+                    results,
+                    request_id,
+                })
+                .await;
+        }
+        WsMessage::Unsubscribe { poll_id } => {
+            subscriptions.lock().await.unsubscribe(&poll_id);
+            let ack = WsMessage::Unsubscribed {
+                poll_id: Some(poll_id),
+                request_id,
+            };
+            outbox.send(ack).await;
+        }
+        WsMessage::SubscribeAll => {
+            subscriptions.lock().await.set_all(true);
+            let ack = WsMessage::Subscribed {
+                poll_id: None,
+                request_id,
+            };
+            outbox.send(ack).await;
+        }
+        WsMessage::UnsubscribeAll => {
+            subscriptions.lock().await.set_all(false);
+            let ack = WsMessage::Unsubscribed {
+                poll_id: None,
+                request_id,
+            };
+            outbox.send(ack).await;
+        }
+        WsMessage::Vote {
+            poll_id,
+            token,
+            choice,
+        } => {
+            let user = user.ok_or(ApiError::NotAuthenticated)?;
+            let access = subscriptions.lock().await.access(&poll_id, token);
+            cast_vote(state, &poll_id, user.user_id, &access, choice.into_ballot()).await?;
+            let ack = WsMessage::Voted {
+                poll_id,
+                request_id,
+            };
+            outbox.send(ack).await;
         }
+        _ => return Err(CommandError::unsupported()),
+    }
+    Ok(())
+}
+
+/// Sends `message`, returning whether the socket is still writable.
+async fn send_message(write: &mut SplitSink<WebSocket, Message>, message: &WsMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(msg) => write.send(Message::Text(msg.into())).await.is_ok(),
+        Err(_) => true,
     }
 }
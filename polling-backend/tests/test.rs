@@ -1,107 +1,151 @@
-use axum::{http::StatusCode, Router};
+use axum::{
+    extract::{Extension, Path},
+    http::{Method, StatusCode},
+    routing::post,
+    Router,
+};
 use axum_test::TestServer;
-use polling::{routes::create_router, state::AppState};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use futures::{SinkExt, StreamExt};
+use polling::{
+    db,
+    error::ApiError,
+    handlers::poll::{cast_vote, create_poll_as},
+    models::{
+        listing::{PollCursor, PollPage},
+        poll::{
+            BallotPrivacy, DeletedPoll, Poll, PollAccess, PollEvent, PollState, PollVisibility,
+            PollVoter, VoteOutcome, VotedPoll, VotingMethod,
+        },
+        results::PollResults,
+    },
+    routes::{create_router, poll_routes},
+    state::AppState,
+    store::StoreError,
+    tally::{
+        engine,
+        irv::{instant_runoff, Transfer},
+        Ballot, Tally, TallyDetail,
+    },
+    tasks::{close_expired_now, open_scheduled_now},
+    websocket::{ClientCommand, Outbox, Subscriptions, WsMessage, MAX_MESSAGE_SIZE},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde_json::json;
-use tower_sessions::MemoryStore;
+use sqlx::MySqlPool;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use tower_sessions::{
+    cookie::time::{Duration as CookieDuration, OffsetDateTime},
+    session::{Id, Record},
+    MemoryStore, Session, SessionManagerLayer, SessionStore,
+};
 use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
 
-// Helper function to create a test app instance
-async fn create_test_app() -> Router {
+fn set_test_env() {
     std::env::set_var("FRONTEND_URL", "http://localhost:3000");
     std::env::set_var("RP_ID", "localhost");
     std::env::set_var("RP_ORIGIN", "http://localhost:3000");
+}
 
-    let state = AppState::new();
+// Helper function to create a test app instance
+async fn create_test_app() -> Router {
+    set_test_env();
+
+    let state = AppState::in_memory();
     let session_store = MemoryStore::default();
     create_router(state, session_store)
 }
 
-// Helper function to authenticate and get session token
-async fn authenticate_user(server: &TestServer, username: &str) -> String {
-    // Start registration
-    let reg_start = server
-        .post(&format!("/api/auth/register_start/{}", username))
-        .await;
-    assert_eq!(reg_start.status_code(), StatusCode::OK);
-
-    // Get session cookie
-    let session_cookie = reg_start
-        .cookies()
-        .iter()
-        .find(|c| c.name() == "webauthn")
-        .expect("No session cookie found")
-        .to_string();
-
-    // Create mock credential
-    let credential_id = base64::encode(Uuid::new_v4().as_bytes());
-    let mock_credential = json!({
-        "id": credential_id.clone(),
-        "rawId": credential_id,
-        "response": {
-            "clientDataJSON": base64::encode(r#"{"type":"webauthn.create","challenge":"","origin":"http://localhost:3000","crossOrigin":false}"#),
-            "attestationObject": base64::encode(r#"{"fmt":"packed","attStmt":{"alg":-7,"sig":"","x5c":[]},"authData":"AUTHDATA"}"#)
-        },
-        "authenticatorAttachment": "platform",
-        "type": "public-key",
-        "clientExtensionResults": {}
-    });
+// Stands in for the WebAuthn ceremony by writing the same session keys
+// `finish_authentication` sets.
+async fn test_login(session: Session, Path(username): Path<String>) -> StatusCode {
+    session.insert("user_id", Uuid::new_v4()).await.unwrap();
+    session.insert("username", username).await.unwrap();
+    StatusCode::OK
+}
 
-    // Complete registration
-    let reg_finish = server
-        .post("/api/auth/register_finish")
-        .add_header("Cookie", &session_cookie)
-        .json(&mock_credential)
-        .await;
-    assert_eq!(reg_finish.status_code(), StatusCode::OK);
+// Helper function to create a server with the poll API and a test login route
+fn create_test_server(state: AppState) -> TestServer {
+    let app = Router::new()
+        .route("/test/login/{username}", post(test_login))
+        .merge(poll_routes())
+        .layer(Extension(state.clone()))
+        .layer(SessionManagerLayer::new(MemoryStore::default()).with_name("webauthn"))
+        .with_state(state);
 
-    // Start authentication
-    let auth_start = server
-        .post(&format!("/api/auth/authenticate_start/{}", username))
-        .await;
-    assert_eq!(auth_start.status_code(), StatusCode::OK);
+    TestServer::new(app).unwrap()
+}
 
-    let auth_session = auth_start
-        .cookies()
-        .iter()
-        .find(|c| c.name() == "webauthn")
-        .expect("No session cookie found")
-        .to_string();
+// In-memory server for most tests
+fn create_memory_test_server() -> TestServer {
+    set_test_env();
+    create_test_server(AppState::in_memory())
+}
 
-    // Complete authentication with same credential
-    let auth_finish = server
-        .post("/api/auth/authenticate_finish")
-        .add_header("Cookie", &auth_session)
-        .json(&mock_credential)
-        .await;
-    assert_eq!(auth_finish.status_code(), StatusCode::OK);
+// Pool for the MySQL database in `DATABASE_URL`, or `None` when no database
+// is configured.
+async fn connect_test_db() -> Option<MySqlPool> {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set, skipping database test");
+        return None;
+    };
+    let pool = db::connect(&database_url)
+        .await
+        .expect("Failed to connect to test database");
+    Some(pool)
+}
 
-    // Get the final session cookie
-    let final_cookie = auth_finish
-        .cookies()
-        .iter()
-        .find(|c| c.name() == "webauthn")
-        .expect("No session cookie found")
-        .to_string();
+// Server backed by the MySQL database in `DATABASE_URL`, or `None` when no
+// database is configured.
+async fn create_db_test_server() -> Option<TestServer> {
+    set_test_env();
+    let pool = connect_test_db().await?;
+    Some(create_test_server(AppState::mysql(pool)))
+}
 
-    // Verify the session is properly set
-    let me_response = server
-        .get("/api/auth/me")
-        .add_header("Cookie", &final_cookie)
-        .await;
-    assert_eq!(me_response.status_code(), StatusCode::OK);
+// A passkey as stored after registration; the key material is never checked
+// by the stores.
+fn sample_passkey(cred_id: &str, counter: u32) -> Passkey {
+    serde_json::from_value(json!({
+        "cred": {
+            "cred_id": cred_id,
+            "cred": {
+                "type_": "ES256",
+                "key": { "EC_EC2": { "curve": "SECP256R1", "x": vec![1u8; 32], "y": vec![2u8; 32] } }
+            },
+            "counter": counter,
+            "transports": null,
+            "user_verified": false,
+            "backup_eligible": false,
+            "backup_state": false,
+            "registration_policy": "preferred",
+            "extensions": { "cred_protect": "NotRequested", "hmac_create_secret": "NotRequested" },
+            "attestation": { "data": "None", "metadata": "None" },
+            "attestation_format": "None"
+        }
+    }))
+    .unwrap()
+}
 
-    // Add debug logging
-    tracing::info!("Session cookie: {}", final_cookie);
-    tracing::info!("Me response: {:?}", me_response);
+// Helper function to authenticate and get session token
+async fn authenticate_user(server: &TestServer, username: &str) -> String {
+    let response = server.post(&format!("/test/login/{}", username)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
 
-    final_cookie
+    format!("webauthn={}", response.cookie("webauthn").value())
 }
 
-/*
 #[tokio::test]
 async fn test_poll_creation() {
-    let app = create_test_app().await;
-    let server = TestServer::new(app).unwrap();
+    let server = create_memory_test_server();
     let auth_token = authenticate_user(&server, "test_user").await;
 
     // Test successful poll creation
@@ -118,18 +162,120 @@ async fn test_poll_creation() {
     let poll: Poll = response.json::<Poll>();
     assert_eq!(poll.title, "Test Poll");
     assert_eq!(poll.options.len(), 2);
+
+    // The poll is read back with its options in order
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.title, poll.title);
+    assert_eq!(fetched.options, poll.options);
+
+    let listed = server.get("/api/polls").await.json::<PollPage>().items;
+    assert!(listed.iter().any(|p| p.id == poll.id));
+}
+
+#[tokio::test]
+async fn test_poll_creation_validation() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    // Fields are trimmed and NFC-normalized before being stored
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "  Cafe\u{0301} poll  ",
+            "options": [" Yes ", "No"]
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!(poll.title, "Caf\u{e9} poll");
+    assert_eq!(poll.options[0].text, "Yes");
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "   ",
+            "options": ["Same", "same", "", "x".repeat(101)]
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "validation_failed");
+    let fields: Vec<(String, String)> = body["details"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| {
+            (
+                f["field"].as_str().unwrap().to_string(),
+                f["code"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("title".to_string(), "required".to_string()),
+            ("options[1]".to_string(), "duplicate".to_string()),
+            ("options[2]".to_string(), "required".to_string()),
+            ("options[3]".to_string(), "too_long".to_string()),
+        ]
+    );
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token)
+        .json(&json!({
+            "title": "Lonely",
+            "options": ["Only one"]
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json::<serde_json::Value>()["details"]["fields"][0]["code"],
+        "too_few"
+    );
+}
+
+#[tokio::test]
+async fn test_poll_limits_are_configurable() {
+    set_test_env();
+    let mut state = AppState::in_memory();
+    state.poll_limits.max_options = 3;
+    let server = create_test_server(state.clone());
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token)
+        .json(&json!({
+            "title": "Too Many",
+            "options": ["A", "B", "C", "D"]
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The shared creation path used by non-REST callers applies the same limits
+    let request = serde_json::from_value(json!({
+        "title": "Too Many",
+        "options": ["A", "B", "C", "D"]
+    }))
+    .unwrap();
+    let err = create_poll_as(&state, "test_user".to_string(), request)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "validation_failed");
 }
 
 #[tokio::test]
 async fn test_poll_voting() {
-    let app = create_test_app().await;
-    let server = TestServer::new(app).unwrap();
+    let server = create_memory_test_server();
     let auth_token = authenticate_user(&server, "test_user").await;
 
     // Create a poll first
     let create_response = server
         .post("/api/polls")
-        .add_header("Cookie", auth_token)
+        .add_header("Cookie", auth_token.clone())
         .json(&json!({
             "title": "Voting Test",
             "options": ["Option 1", "Option 2"]
@@ -141,6 +287,7 @@ async fn test_poll_voting() {
     // Test voting
     let vote_response = server
         .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token.clone())
         .json(&json!({
             "option_id": poll.options[0].id
         }))
@@ -149,12 +296,15 @@ async fn test_poll_voting() {
     assert_eq!(vote_response.status_code(), StatusCode::OK);
     let updated_poll: Poll = vote_response.json::<Poll>();
     assert_eq!(updated_poll.options[0].votes, 1);
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.options[0].votes, 1);
+    assert_eq!(fetched.options[1].votes, 0);
 }
 
 #[tokio::test]
 async fn test_poll_management() {
-    let app = create_test_app().await;
-    let server = TestServer::new(app).unwrap();
+    let server = create_memory_test_server();
     let auth_token = authenticate_user(&server, "test_user").await;
 
     // Create a poll
@@ -168,6 +318,14 @@ async fn test_poll_management() {
         .await
         .json::<Poll>();
 
+    server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "option_id": poll.options[1].id
+        }))
+        .await;
+
     // Test closing poll
     let close_response = server
         .post(&format!("/api/polls/{}/close", poll.id))
@@ -190,22 +348,3242 @@ async fn test_poll_management() {
         reset_poll.options.iter().map(|opt| opt.votes).sum::<i32>(),
         0
     );
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert!(fetched.is_closed);
+    assert_eq!(fetched.options.iter().map(|opt| opt.votes).sum::<i32>(), 0);
 }
-*/
 
 #[tokio::test]
-async fn test_unauthorized_access() {
-    let app = create_test_app().await;
-    let server = TestServer::new(app).unwrap();
+async fn test_vote_requires_authentication() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "creator").await;
 
-    // Try to create poll without authentication
-    let response = server
+    let poll: Poll = server
         .post("/api/polls")
+        .add_header("Cookie", auth_token)
         .json(&json!({
-            "title": "Unauthorized Poll",
+            "title": "Anonymous Vote",
             "options": ["Option 1", "Option 2"]
         }))
+        .await
+        .json::<Poll>();
+
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .json(&json!({
+            "option_id": poll.options[0].id
+        }))
         .await;
 
     assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn test_duplicate_vote_rejected() {
+    let server = create_memory_test_server();
+    let creator = authenticate_user(&server, "creator").await;
+    let voter = authenticate_user(&server, "voter").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "One Vote Each",
+            "options": ["Option 1", "Option 2"]
+        }))
+        .await
+        .json::<Poll>();
+
+    for token in [&creator, &voter] {
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", token.clone())
+            .json(&json!({
+                "option_id": poll.options[0].id
+            }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    // Voting again, even for a different option, is refused
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", voter)
+        .json(&json!({
+            "option_id": poll.options[1].id
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.options[0].votes, 2);
+    assert_eq!(fetched.options[1].votes, 0);
+}
+
+#[tokio::test]
+async fn test_vote_change_moves_ballot() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Changeable",
+            "options": ["Option 1", "Option 2"],
+            "allow_vote_change": true
+        }))
+        .await
+        .json::<Poll>();
+    assert!(poll.allow_vote_change);
+
+    for option in [&poll.options[0], &poll.options[1], &poll.options[1]] {
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", auth_token.clone())
+            .json(&json!({
+                "option_id": option.id
+            }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.options[0].votes, 0);
+    assert_eq!(fetched.options[1].votes, 1);
+}
+
+#[tokio::test]
+async fn test_ballots_for_deleted_polls_are_not_stored() {
+    let state = AppState::in_memory();
+    let request =
+        serde_json::from_value(json!({ "title": "Vanishing", "options": ["A", "B"] })).unwrap();
+    let poll = create_poll_as(&state, "alice".to_string(), request)
+        .await
+        .unwrap();
+    let ballot = Ballot::new(vec![poll.options[0].id.clone()]);
+
+    // Deleted after the handler looked it up, before the ballot is written
+    state
+        .polls
+        .soft_delete_poll(&poll.id, Utc::now())
+        .await
+        .unwrap();
+    for poll_id in [poll.id.as_str(), "missing"] {
+        let outcome = state
+            .polls
+            .cast_ballot(poll_id, Uuid::new_v4(), &ballot, false)
+            .await
+            .unwrap();
+        assert_eq!(outcome, VoteOutcome::PollNotFound);
+    }
+    assert!(state.polls.ballots(&poll.id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_multiple_choice_poll() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Impossible",
+            "options": ["A", "B"],
+            "max_choices": 3
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Pick Two",
+            "options": ["A", "B", "C"],
+            "min_choices": 1,
+            "max_choices": 2
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!((poll.min_choices, poll.max_choices), (1, 2));
+
+    for (option_ids, code) in [
+        (vec![&poll.options[0].id; 2], "duplicate"),
+        (poll.options.iter().map(|opt| &opt.id).collect(), "too_many"),
+        (vec![], "too_few"),
+    ] {
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", auth_token.clone())
+            .json(&json!({ "option_ids": option_ids }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.json::<serde_json::Value>()["details"]["fields"][0]["code"],
+            code
+        );
+    }
+
+    let voted: Poll = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "option_ids": [poll.options[0].id, poll.options[2].id] }))
+        .await
+        .json();
+    let votes: Vec<i32> = voted.options.iter().map(|opt| opt.votes).collect();
+    assert_eq!(votes, vec![1, 0, 1]);
+    assert_eq!(voted.total_votes, 2);
+
+    // The websocket vote payload accepts the same shapes as the REST one
+    let message: WsMessage = serde_json::from_value(json!({
+        "type": "Vote",
+        "poll_id": poll.id,
+        "option_ids": [poll.options[0].id, poll.options[1].id]
+    }))
+    .unwrap();
+    let WsMessage::Vote { choice, .. } = message else {
+        panic!("expected a vote");
+    };
+    assert_eq!(choice.selections().len(), 2);
+    let message: WsMessage = serde_json::from_value(json!({
+        "type": "Vote",
+        "poll_id": poll.id,
+        "option_id": poll.options[0].id
+    }))
+    .unwrap();
+    let WsMessage::Vote { choice, .. } = message else {
+        panic!("expected a vote");
+    };
+    assert_eq!(choice.selections(), vec![poll.options[0].id.clone()]);
+}
+
+#[tokio::test]
+async fn test_anonymous_and_attributed_ballots() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let creator = authenticate_user(&server, "creator").await;
+    let voter = authenticate_user(&server, "voter").await;
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Secret But Changeable",
+            "options": ["Option 1", "Option 2"],
+            "ballot_privacy": "anonymous",
+            "allow_vote_change": true
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let anonymous: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Secret Ballot",
+            "options": ["Option 1", "Option 2"],
+            "ballot_privacy": "anonymous"
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!(anonymous.ballot_privacy, BallotPrivacy::Anonymous);
+
+    for expected in [StatusCode::OK, StatusCode::CONFLICT] {
+        let response = server
+            .post(&format!("/api/polls/{}/vote", anonymous.id))
+            .add_header("Cookie", voter.clone())
+            .json(&json!({ "option_id": anonymous.options[1].id }))
+            .await;
+        assert_eq!(response.status_code(), expected);
+    }
+    let ballots = state.polls.ballots(&anonymous.id).await.unwrap();
+    assert_eq!(
+        ballots,
+        vec![Ballot::new(vec![anonymous.options[1].id.clone()])]
+    );
+    assert!(state
+        .polls
+        .attributed_ballots(&anonymous.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let response = server
+        .get(&format!("/api/polls/{}/voters", anonymous.id))
+        .add_header("Cookie", creator.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "anonymous_ballots"
+    );
+
+    let attributed: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Open Ballot",
+            "options": ["Option 1", "Option 2"]
+        }))
+        .await
+        .json::<Poll>();
+    for (cookie, option) in [(&voter, 0), (&creator, 1)] {
+        server
+            .post(&format!("/api/polls/{}/vote", attributed.id))
+            .add_header("Cookie", cookie.clone())
+            .json(&json!({ "option_id": attributed.options[option].id }))
+            .await;
+    }
+
+    let response = server
+        .get(&format!("/api/polls/{}/voters", attributed.id))
+        .add_header("Cookie", voter)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let voters: Vec<PollVoter> = server
+        .get(&format!("/api/polls/{}/voters", attributed.id))
+        .add_header("Cookie", creator)
+        .await
+        .json();
+    let mut chosen: Vec<&String> = voters.iter().map(|v| &v.ballot.option_ids[0]).collect();
+    chosen.sort();
+    let mut expected: Vec<&String> = attributed.options.iter().map(|opt| &opt.id).collect();
+    expected.sort();
+    assert_eq!(chosen, expected);
+}
+
+#[tokio::test]
+async fn test_total_votes_track_every_mutation() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let creator = authenticate_user(&server, "creator").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Totals",
+            "options": ["Option 1", "Option 2"],
+            "allow_vote_change": true
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!(poll.total_votes, 0);
+
+    // REST votes
+    let mut voters = Vec::new();
+    for (name, option) in [("a", 0), ("b", 0), ("c", 1)] {
+        let token = authenticate_user(&server, name).await;
+        let voted: Poll = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", token.clone())
+            .json(&json!({
+                "option_id": poll.options[option].id
+            }))
+            .await
+            .json();
+        assert_eq!(
+            voted.total_votes,
+            voted.options.iter().map(|o| o.votes).sum::<i32>()
+        );
+        voters.push(token);
+    }
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.total_votes, 3);
+    assert_eq!(fetched.options[0].percentage, 66.67);
+    assert_eq!(fetched.options[1].percentage, 33.33);
+
+    // Changing a vote moves it without changing the total
+    let changed: Poll = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", voters[0].clone())
+        .json(&json!({
+            "option_id": poll.options[1].id
+        }))
+        .await
+        .json();
+    assert_eq!(changed.total_votes, 3);
+    assert_eq!(changed.options[1].votes, 2);
+
+    // The websocket vote path goes through `cast_vote` and broadcasts the tally
+    let mut updates = state.poll_updates.subscribe();
+    cast_vote(
+        &state,
+        &poll.id,
+        Uuid::new_v4(),
+        &PollAccess::default(),
+        Ballot::new(vec![poll.options[0].id.clone()]),
+    )
+    .await
+    .unwrap();
+    let PollEvent::Updated(broadcast) = updates.recv().await.unwrap() else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(broadcast.total_votes, 4);
+    assert_eq!(broadcast.options[0].percentage, 50.0);
+
+    let listed = server.get("/api/polls").await.json::<PollPage>().items;
+    let listed = listed.iter().find(|p| p.id == poll.id).unwrap();
+    assert_eq!(listed.total_votes, 4);
+
+    // Resetting clears the total and percentages
+    let reset: Poll = server
+        .post(&format!("/api/polls/{}/reset", poll.id))
+        .add_header("Cookie", creator)
+        .await
+        .json();
+    assert_eq!(reset.total_votes, 0);
+    assert!(reset.options.iter().all(|o| o.percentage == 0.0));
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.total_votes, 0);
+}
+
+#[tokio::test]
+async fn test_scheduled_poll_window() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Backwards",
+            "options": ["Option 1", "Option 2"],
+            "opens_at": Utc::now() + Duration::hours(2),
+            "closes_at": Utc::now() + Duration::hours(1)
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let future: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Tomorrow",
+            "options": ["Option 1", "Option 2"],
+            "opens_at": Utc::now() + Duration::days(1)
+        }))
+        .await
+        .json::<Poll>();
+    let response = server
+        .post(&format!("/api/polls/{}/vote", future.id))
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({ "option_id": future.options[0].id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "poll_not_open"
+    );
+
+    let expiring: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Time Boxed",
+            "options": ["Option 1", "Option 2"],
+            "closes_at": Utc::now() + Duration::milliseconds(200)
+        }))
+        .await
+        .json::<Poll>();
+    assert!(expiring.closes_at.is_some());
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+    // Votes are refused as soon as the deadline passes, even before the sweep
+    let response = server
+        .post(&format!("/api/polls/{}/vote", expiring.id))
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "option_id": expiring.options[0].id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "poll_closed");
+
+    let mut updates = state.poll_updates.subscribe();
+    close_expired_now(&state).await;
+    let PollEvent::Updated(closed) = updates.recv().await.unwrap() else {
+        panic!("expected an update for the expired poll");
+    };
+    assert_eq!(closed.id, expiring.id);
+    assert!(closed.is_closed);
+    assert!(updates.try_recv().is_err());
+
+    let fetched: Poll = server
+        .get(&format!("/api/polls/{}", expiring.id))
+        .await
+        .json();
+    assert!(fetched.is_closed);
+    let fetched: Poll = server
+        .get(&format!("/api/polls/{}", future.id))
+        .await
+        .json();
+    assert!(!fetched.is_closed);
+}
+
+#[tokio::test]
+async fn test_poll_results() {
+    let server = create_memory_test_server();
+    let creator = authenticate_user(&server, "creator").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Results",
+            "options": ["Option 1", "Option 2", "Option 3"]
+        }))
+        .await
+        .json::<Poll>();
+
+    for (name, option) in [("a", 1), ("b", 1), ("c", 2), ("d", 0), ("e", 2)] {
+        let token = authenticate_user(&server, name).await;
+        server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", token)
+            .json(&json!({
+                "option_id": poll.options[option].id
+            }))
+            .await;
+    }
+
+    let results: PollResults = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_query_param("live", true)
+        .await
+        .json();
+    assert_eq!(results.total_votes, 5);
+    let ranked: Vec<(&str, u32)> = results
+        .options
+        .iter()
+        .map(|o| (o.id.as_str(), o.rank))
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![
+            (poll.options[1].id.as_str(), 1),
+            (poll.options[2].id.as_str(), 1),
+            (poll.options[0].id.as_str(), 3),
+        ]
+    );
+    assert!(results.is_tie);
+    assert_eq!(results.winner, None);
+
+    // Filters that don't match the poll hide it
+    for (key, value) in [("closed", "true"), ("creator", "someone_else")] {
+        let response = server
+            .get(&format!("/api/polls/{}/results", poll.id))
+            .add_query_param(key, value)
+            .await;
+        assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    // One more vote breaks the tie; closing makes it the winner
+    let token = authenticate_user(&server, "f").await;
+    server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", token)
+        .json(&json!({
+            "option_id": poll.options[2].id
+        }))
+        .await;
+    server
+        .post(&format!("/api/polls/{}/close", poll.id))
+        .add_header("Cookie", creator)
+        .await;
+
+    let response = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_query_param("live", true)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+    let results: PollResults = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_query_param("closed", true)
+        .add_query_param("creator", "creator")
+        .await
+        .json();
+    assert!(!results.is_tie);
+    assert_eq!(results.winner.as_deref(), Some(poll.options[2].id.as_str()));
+}
+
+fn ballots(spec: &[(usize, &[&str])]) -> Vec<Ballot> {
+    spec.iter()
+        .flat_map(|(count, ranking)| std::iter::repeat_n(Ballot::new(ids(ranking)), *count))
+        .collect()
+}
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn test_instant_runoff_transfers_eliminated_votes() {
+    let runoff = instant_runoff(
+        &ids(&["a", "b", "c"]),
+        &ballots(&[
+            (4, &["a", "b", "c"]),
+            (3, &["b", "c", "a"]),
+            (2, &["c", "b", "a"]),
+        ]),
+    );
+
+    assert_eq!(runoff.winner.as_deref(), Some("b"));
+    assert_eq!(runoff.rounds.len(), 2);
+    let first = &runoff.rounds[0];
+    let votes: Vec<u32> = first.tallies.iter().map(|t| t.votes).collect();
+    assert_eq!(votes, vec![4, 3, 2]);
+    assert_eq!(first.eliminated, ids(&["c"]));
+    assert_eq!(
+        first.transfers,
+        vec![Transfer {
+            from: "c".to_string(),
+            to: Some("b".to_string()),
+            votes: 2
+        }]
+    );
+    let votes: Vec<u32> = runoff.rounds[1].tallies.iter().map(|t| t.votes).collect();
+    assert_eq!(votes, vec![4, 5]);
+}
+
+#[test]
+fn test_instant_runoff_exhausts_and_ties() {
+    // Both last-placed options drop together and their ballots run out
+    let runoff = instant_runoff(
+        &ids(&["a", "b", "c"]),
+        &ballots(&[(2, &["a"]), (1, &["b"]), (1, &["c", "b"])]),
+    );
+    assert_eq!(runoff.winner.as_deref(), Some("a"));
+    assert_eq!(runoff.rounds[0].eliminated, ids(&["b", "c"]));
+    assert!(runoff.rounds[0].transfers.iter().all(|t| t.to.is_none()));
+    assert_eq!(runoff.rounds[1].exhausted, 2);
+
+    let runoff = instant_runoff(&ids(&["a", "b"]), &ballots(&[(1, &["a"]), (1, &["b"])]));
+    assert_eq!(runoff.winner, None);
+    assert_eq!(runoff.rounds.len(), 1);
+
+    let runoff = instant_runoff(&ids(&["a", "b"]), &[]);
+    assert!(runoff.rounds.is_empty());
+    assert_eq!(runoff.winner, None);
+}
+
+#[test]
+fn test_approval_and_score_tallies() {
+    let options = ids(&["a", "b", "c"]);
+
+    let tally = engine(VotingMethod::Approval).count(
+        &options,
+        &ballots(&[(2, &["a", "b"]), (1, &["b", "c"]), (1, &["c"])]),
+    );
+    let TallyDetail::Approval { totals } = &tally.detail else {
+        panic!("expected approval totals");
+    };
+    let votes: Vec<u32> = totals.iter().map(|t| t.votes).collect();
+    assert_eq!(votes, vec![2, 3, 2]);
+    assert_eq!(tally.winner().map(String::as_str), Some("b"));
+
+    let scored = |scores: &[(&str, u32)]| {
+        Ballot::scored(
+            scores
+                .iter()
+                .map(|(id, score)| (id.to_string(), *score))
+                .collect(),
+        )
+    };
+    let tally = engine(VotingMethod::Score).count(
+        &options,
+        &[
+            scored(&[("a", 5), ("b", 3)]),
+            scored(&[("a", 0), ("b", 4), ("c", 5)]),
+        ],
+    );
+    let TallyDetail::Score { scores } = &tally.detail else {
+        panic!("expected score totals");
+    };
+    let totals: Vec<u32> = scores.iter().map(|s| s.total).collect();
+    assert_eq!(totals, vec![5, 7, 5]);
+    assert_eq!(scores[1].average, 3.5);
+    assert_eq!(tally.leaders, ids(&["b"]));
+
+    // Nothing counted means nobody leads
+    let tally = engine(VotingMethod::Score).count(&options, &[]);
+    assert!(tally.leaders.is_empty());
+}
+
+#[test]
+fn test_schulze_pairwise_and_paths() {
+    let options = ids(&["a", "b", "c", "d", "e"]);
+    let tally = engine(VotingMethod::Condorcet).count(
+        &options,
+        &ballots(&[
+            (5, &["a", "c", "b", "e", "d"]),
+            (5, &["a", "d", "e", "c", "b"]),
+            (8, &["b", "e", "d", "a", "c"]),
+            (3, &["c", "a", "b", "e", "d"]),
+            (7, &["c", "a", "e", "b", "d"]),
+            (2, &["c", "b", "a", "d", "e"]),
+            (7, &["d", "c", "e", "b", "a"]),
+            (8, &["e", "b", "a", "d", "c"]),
+        ]),
+    );
+    let TallyDetail::Condorcet { condorcet } = &tally.detail else {
+        panic!("expected a condorcet tally");
+    };
+
+    assert_eq!(condorcet.pairwise[0], vec![0, 20, 26, 30, 22]);
+    assert_eq!(condorcet.pairwise[1][0], 25);
+    assert_eq!(condorcet.strongest_paths[4], vec![25, 28, 28, 31, 0]);
+    assert_eq!(condorcet.condorcet_winner, None);
+    assert_eq!(tally.leaders, ids(&["e"]));
+
+    // Unranked options lose to ranked ones and tie among themselves
+    let tally = engine(VotingMethod::Condorcet).count(
+        &ids(&["a", "b", "c"]),
+        &ballots(&[(2, &["b"]), (1, &["a", "c"])]),
+    );
+    let TallyDetail::Condorcet { condorcet } = &tally.detail else {
+        panic!("expected a condorcet tally");
+    };
+    assert_eq!(condorcet.pairwise[1], vec![2, 0, 2]);
+    assert_eq!(condorcet.pairwise[0][2], 1);
+    assert_eq!(condorcet.condorcet_winner.as_deref(), Some("b"));
+    assert_eq!(tally.leaders, ids(&["b"]));
+}
+
+#[tokio::test]
+async fn test_score_poll_results() {
+    let server = create_memory_test_server();
+    let creator = authenticate_user(&server, "creator").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Rate The Venues",
+            "options": ["Hall", "Park"],
+            "voting_method": "score",
+            "max_score": 10
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!(poll.max_score, 10);
+    let (hall, park) = (poll.options[0].id.clone(), poll.options[1].id.clone());
+
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", creator.clone())
+        .json(&json!({ "scores": { hall.clone(): 11 } }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", creator.clone())
+        .json(&json!({ "option_id": hall.clone() }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    for scores in [
+        json!({ hall.clone(): 10, park.clone(): 2 }),
+        json!({ hall.clone(): 1, park.clone(): 7 }),
+        json!({ park.clone(): 6 }),
+    ] {
+        let voter = authenticate_user(&server, "voter").await;
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", voter)
+            .json(&json!({ "scores": scores }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    let results: serde_json::Value = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .await
+        .json();
+    assert_eq!(results["tally"]["method"], "score");
+    assert_eq!(results["tally"]["scores"][0]["total"], 11);
+    assert_eq!(results["tally"]["scores"][1]["total"], 15);
+    assert_eq!(results["leaders"], json!([park]));
+    // Ranked by total score, not by how many ballots scored each option
+    assert_eq!(results["options"][0]["id"], json!(park));
+    assert_eq!(results["options"][0]["rank"], 1);
+    assert_eq!(results["options"][1]["rank"], 2);
+}
+
+#[tokio::test]
+async fn test_ranked_choice_poll() {
+    let server = create_memory_test_server();
+    let creator = authenticate_user(&server, "creator").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Team Lead",
+            "options": ["Ada", "Brian", "Cleo"],
+            "voting_method": "ranked_choice"
+        }))
+        .await
+        .json::<Poll>();
+    assert_eq!(poll.voting_method, VotingMethod::RankedChoice);
+    assert_eq!((poll.min_choices, poll.max_choices), (1, 3));
+
+    let [a, b, c] = [0, 1, 2].map(|i| poll.options[i].id.clone());
+    let rankings = [
+        (4, vec![&a, &b, &c]),
+        (3, vec![&b, &c, &a]),
+        (2, vec![&c, &b]),
+    ];
+    for (count, ranking) in rankings {
+        for _ in 0..count {
+            let voter = authenticate_user(&server, "voter").await;
+            let response = server
+                .post(&format!("/api/polls/{}/vote", poll.id))
+                .add_header("Cookie", voter)
+                .json(&json!({ "option_ids": ranking }))
+                .await;
+            assert_eq!(response.status_code(), StatusCode::OK);
+        }
+    }
+
+    let results: PollResults = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .await
+        .json();
+    // Option counts are first preferences; the runoff decides the leader
+    let votes: Vec<i32> = results.options.iter().map(|opt| opt.votes).collect();
+    assert_eq!(votes, vec![3, 4, 2]);
+    let Some(Tally {
+        detail: TallyDetail::RankedChoice { runoff },
+        ..
+    }) = results.tally
+    else {
+        panic!("expected a runoff tally");
+    };
+    assert_eq!(runoff.rounds.len(), 2);
+    assert_eq!(runoff.rounds[0].eliminated, vec![c.clone()]);
+    assert_eq!(results.leaders, vec![b.clone()]);
+    // The first-preference leader lost the runoff, so it ranks second
+    let ranked: Vec<(&str, u32)> = results
+        .options
+        .iter()
+        .map(|opt| (opt.id.as_str(), opt.rank))
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![(b.as_str(), 1), (a.as_str(), 2), (c.as_str(), 3)]
+    );
+    assert_eq!(results.winner, None);
+
+    server
+        .post(&format!("/api/polls/{}/close", poll.id))
+        .add_header("Cookie", creator)
+        .await;
+    let results: PollResults = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .await
+        .json();
+    assert_eq!(results.winner, Some(b));
+}
+
+#[tokio::test]
+async fn test_results_hidden_until_close() {
+    let server = create_memory_test_server();
+    let creator = authenticate_user(&server, "creator").await;
+    let voter = authenticate_user(&server, "voter").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Secret Until Close",
+            "options": ["Option 1", "Option 2"],
+            "results_visibility": "after_close"
+        }))
+        .await
+        .json::<Poll>();
+
+    let voted: Poll = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", voter.clone())
+        .json(&json!({
+            "option_id": poll.options[0].id
+        }))
+        .await
+        .json();
+    assert_eq!(voted.total_votes, 0);
+
+    let response = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_header("Cookie", voter.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.options[0].votes, 0);
+
+    // The creator can always peek
+    let response = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_header("Cookie", creator.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    server
+        .post(&format!("/api/polls/{}/close", poll.id))
+        .add_header("Cookie", creator)
+        .await;
+
+    let results: PollResults = server
+        .get(&format!("/api/polls/{}/results", poll.id))
+        .add_header("Cookie", voter)
+        .await
+        .json();
+    assert_eq!(results.total_votes, 1);
+    assert_eq!(results.winner.as_deref(), Some(poll.options[0].id.as_str()));
+}
+
+#[tokio::test]
+async fn test_delete_and_restore_poll() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let creator = authenticate_user(&server, "creator").await;
+    let other = authenticate_user(&server, "other").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator.clone())
+        .json(&json!({
+            "title": "Short Lived",
+            "options": ["Option 1", "Option 2"]
+        }))
+        .await
+        .json::<Poll>();
+
+    let response = server
+        .delete(&format!("/api/polls/{}", poll.id))
+        .add_header("Cookie", other.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let mut updates = state.poll_updates.subscribe();
+    let deleted: DeletedPoll = server
+        .delete(&format!("/api/polls/{}", poll.id))
+        .add_header("Cookie", creator.clone())
+        .await
+        .json();
+    assert_eq!(deleted.poll_id, poll.id);
+    assert!(deleted.restorable_until > deleted.deleted_at);
+
+    // Subscribers get a dedicated deletion event
+    match updates.recv().await.unwrap() {
+        PollEvent::Deleted(gone) => assert_eq!(gone.id, poll.id),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    let listed = server.get("/api/polls").await.json::<PollPage>().items;
+    assert!(listed.iter().all(|p| p.id != poll.id));
+
+    let response = server
+        .post(&format!("/api/polls/{}/restore", poll.id))
+        .add_header("Cookie", other)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let restored: Poll = server
+        .post(&format!("/api/polls/{}/restore", poll.id))
+        .add_header("Cookie", creator.clone())
+        .await
+        .json();
+    assert_eq!(restored.id, poll.id);
+    let listed = server.get("/api/polls").await.json::<PollPage>().items;
+    assert!(listed.iter().any(|p| p.id == poll.id));
+
+    // Once purged, the poll is gone for good
+    server
+        .delete(&format!("/api/polls/{}", poll.id))
+        .add_header("Cookie", creator.clone())
+        .await;
+    let purged = state
+        .polls
+        .purge_deleted(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(purged, vec![poll.id.clone()]);
+
+    let response = server
+        .post(&format!("/api/polls/{}/restore", poll.id))
+        .add_header("Cookie", creator)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_mysql_poll_round_trip() {
+    let Some(server) = create_db_test_server().await else {
+        return;
+    };
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "MySQL Test",
+            "options": ["Option 1", "Option 2", "Option 3"]
+        }))
+        .await
+        .json::<Poll>();
+
+    let fetched: Poll = server.get(&format!("/api/polls/{}", poll.id)).await.json();
+    assert_eq!(fetched.title, poll.title);
+    assert_eq!(fetched.options, poll.options);
+
+    let voted: Poll = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "option_id": poll.options[2].id
+        }))
+        .await
+        .json();
+    assert_eq!(voted.options[2].votes, 1);
+    assert_eq!(voted.total_votes, 1);
+    assert_eq!(voted.options[2].percentage, 100.0);
+
+    let closed: Poll = server
+        .post(&format!("/api/polls/{}/close", poll.id))
+        .add_header("Cookie", auth_token.clone())
+        .await
+        .json();
+    assert!(closed.is_closed);
+
+    let reset: Poll = server
+        .post(&format!("/api/polls/{}/reset", poll.id))
+        .add_header("Cookie", auth_token)
+        .await
+        .json();
+    assert_eq!(reset.options.iter().map(|opt| opt.votes).sum::<i32>(), 0);
+
+    let listed = server.get("/api/polls").await.json::<PollPage>().items;
+    let listed = listed.iter().find(|p| p.id == poll.id).unwrap();
+    assert!(listed.is_closed);
+    assert_eq!(listed.options.iter().map(|opt| opt.votes).sum::<i32>(), 0);
+}
+
+#[tokio::test]
+async fn test_mysql_anonymous_voters_vote_once() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    set_test_env();
+    let state = AppState::mysql(pool);
+    let server = create_test_server(state.clone());
+    let creator = authenticate_user(&server, "creator").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", creator)
+        .json(&json!({
+            "title": "Secret Ballot",
+            "options": ["Option 1", "Option 2"],
+            "ballot_privacy": "anonymous"
+        }))
+        .await
+        .json();
+    let voter_id = Uuid::new_v4();
+    let ballot = Ballot::new(vec![poll.options[1].id.clone()]);
+
+    let first = state
+        .polls
+        .cast_ballot(&poll.id, voter_id, &ballot, false)
+        .await
+        .unwrap();
+    assert_eq!(first, VoteOutcome::Recorded);
+    let second = state
+        .polls
+        .cast_ballot(&poll.id, voter_id, &ballot, false)
+        .await
+        .unwrap();
+    assert_eq!(second, VoteOutcome::AlreadyVoted);
+
+    assert_eq!(state.polls.ballots(&poll.id).await.unwrap(), vec![ballot]);
+    assert!(state
+        .polls
+        .attributed_ballots(&poll.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The voter sees the poll among theirs, without a ballot
+    let voted = state.polls.voted_polls(voter_id).await.unwrap();
+    assert_eq!(voted.len(), 1);
+    assert_eq!(voted[0].0.id, poll.id);
+    assert_eq!(voted[0].0.total_votes, 1);
+    assert_eq!(voted[0].1, None);
+}
+
+#[tokio::test]
+async fn test_mysql_anonymous_ballots_carry_no_time() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    set_test_env();
+    let server = create_test_server(AppState::mysql(pool.clone()));
+    let creator = authenticate_user(&server, "creator").await;
+
+    let mut polls = Vec::new();
+    for privacy in ["anonymous", "attributed"] {
+        let poll: Poll = server
+            .post("/api/polls")
+            .add_header("Cookie", creator.clone())
+            .json(&json!({
+                "title": "When Did You Vote",
+                "options": ["A", "B"],
+                "ballot_privacy": privacy
+            }))
+            .await
+            .json();
+        let voter = authenticate_user(&server, "voter").await;
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", voter)
+            .json(&json!({ "option_id": poll.options[0].id }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        polls.push(poll);
+    }
+
+    // Only the attributed ballot records when it was cast
+    for (poll, expected) in polls.iter().zip([0, 1]) {
+        let timed: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM votes WHERE poll_id = ? AND created_at IS NOT NULL",
+        )
+        .bind(&poll.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(timed, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_mysql_deleted_polls_restore_and_purge() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    set_test_env();
+    let state = AppState::mysql(pool);
+    let server = create_test_server(state.clone());
+    let auth_token = authenticate_user(&server, "deleter").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({ "title": "Short Lived", "options": ["Option 1", "Option 2"] }))
+        .await
+        .json();
+    server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "option_id": poll.options[0].id }))
+        .await;
+
+    let deleted_at = Utc::now();
+    state
+        .polls
+        .soft_delete_poll(&poll.id, deleted_at)
+        .await
+        .unwrap();
+    assert!(state.polls.get_poll(&poll.id).await.unwrap().is_none());
+    let (hidden, _) = state
+        .polls
+        .get_deleted_poll(&poll.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(hidden.total_votes, 1);
+
+    state.polls.restore_poll(&poll.id).await.unwrap();
+    let restored = state.polls.get_poll(&poll.id).await.unwrap().unwrap();
+    assert_eq!(restored.total_votes, 1);
+
+    // Purging removes the poll along with its ballots
+    state
+        .polls
+        .soft_delete_poll(&poll.id, deleted_at)
+        .await
+        .unwrap();
+    let purged = state
+        .polls
+        .purge_deleted(deleted_at - Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(!purged.contains(&poll.id));
+    let purged = state
+        .polls
+        .purge_deleted(deleted_at + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(purged.contains(&poll.id));
+    assert!(state
+        .polls
+        .get_deleted_poll(&poll.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(state.polls.ballots(&poll.id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_usernames_stay_with_their_first_user() {
+    let state = AppState::in_memory();
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+    // Two registrations for the same new name; the second one to finish loses
+    state
+        .users
+        .add_passkey(first, "alice", &sample_passkey("AQID", 0))
+        .await
+        .unwrap();
+    let result = state
+        .users
+        .add_passkey(second, "alice", &sample_passkey("BAUG", 0))
+        .await;
+    assert!(matches!(result, Err(StoreError::UsernameTaken(name)) if name == "alice"));
+    assert_eq!(
+        state.users.find_user_id("alice").await.unwrap(),
+        Some(first)
+    );
+    assert!(state.users.passkeys(second).await.unwrap().is_empty());
+
+    // Further passkeys for the same user are fine
+    state
+        .users
+        .add_passkey(first, "alice", &sample_passkey("BwgJ", 0))
+        .await
+        .unwrap();
+    assert_eq!(state.users.passkeys(first).await.unwrap().len(), 2);
+}
+
+fn passkey_counter(passkey: &Passkey) -> u64 {
+    serde_json::to_value(passkey).unwrap()["cred"]["counter"]
+        .as_u64()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_passkey_counters_are_persisted() {
+    let state = AppState::in_memory();
+    let user_id = Uuid::new_v4();
+
+    // Registered, then authenticated, which bumps the counter
+    state
+        .users
+        .add_passkey(user_id, "alice", &sample_passkey("AQID", 0))
+        .await
+        .unwrap();
+    state
+        .users
+        .update_passkey(&sample_passkey("AQID", 7))
+        .await
+        .unwrap();
+
+    let keys = state.users.passkeys(user_id).await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(passkey_counter(&keys[0]), 7);
+}
+
+#[tokio::test]
+async fn test_mysql_passkey_counters_are_persisted() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    let username = format!("counter-{}", Uuid::new_v4());
+    let user_id = Uuid::new_v4();
+    let cred_id = URL_SAFE_NO_PAD.encode(Uuid::new_v4().as_bytes());
+
+    assert!(
+        db::user::insert_credential(&pool, user_id, &username, &sample_passkey(&cred_id, 0))
+            .await
+            .unwrap()
+    );
+    db::user::update_credential(&pool, &sample_passkey(&cred_id, 7))
+        .await
+        .unwrap();
+
+    let keys = db::user::fetch_passkeys(&pool, user_id).await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(passkey_counter(&keys[0]), 7);
+    let counter: u32 = sqlx::query_scalar("SELECT counter FROM credentials WHERE user_id = ?")
+        .bind(user_id.to_string())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(counter, 7);
+}
+
+#[tokio::test]
+async fn test_mysql_usernames_stay_with_their_first_user() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    let username = format!("racer-{}", Uuid::new_v4());
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    let cred_id = || URL_SAFE_NO_PAD.encode(Uuid::new_v4().as_bytes());
+
+    let stored =
+        db::user::insert_credential(&pool, first, &username, &sample_passkey(&cred_id(), 0))
+            .await
+            .unwrap();
+    assert!(stored);
+    let stored =
+        db::user::insert_credential(&pool, second, &username, &sample_passkey(&cred_id(), 0))
+            .await
+            .unwrap();
+    assert!(!stored);
+    assert_eq!(
+        db::user::find_user_id(&pool, &username).await.unwrap(),
+        Some(first)
+    );
+    assert!(db::user::fetch_passkeys(&pool, second)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_error_responses_carry_codes() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let response = server.get("/api/polls/missing").await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "poll_not_found");
+    assert_eq!(body["details"]["poll_id"], "missing");
+    assert!(body["error"].is_string());
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Error Codes",
+            "options": ["Option 1", "Option 2"]
+        }))
+        .await
+        .json::<Poll>();
+
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({ "option_id": "nope" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "option_not_found"
+    );
+
+    server
+        .post(&format!("/api/polls/{}/close", poll.id))
+        .add_header("Cookie", auth_token.clone())
+        .await;
+    let response = server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "option_id": poll.options[0].id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "poll_closed");
+
+    let response = server.post(&format!("/api/polls/{}/close", poll.id)).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "not_authenticated"
+    );
+}
+
+#[tokio::test]
+async fn test_poll_listing_pages_filters_and_sorts() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+
+    let mut created = Vec::new();
+    for (i, (token, title)) in [
+        (&alice, "Lunch spot"),
+        (&bob, "Team lunch day"),
+        (&alice, "Best editor"),
+        (&bob, "Release name"),
+        (&alice, "Office plants"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let mut body = json!({ "title": title, "options": ["Yes", "No"] });
+        if i < 2 {
+            body["closes_at"] = json!(Utc::now() + Duration::hours(10 - i as i64));
+        }
+        let poll: Poll = server
+            .post("/api/polls")
+            .add_header("Cookie", token.clone())
+            .json(&body)
+            .await
+            .json();
+        created.push(poll);
+    }
+    for votes in 0..3 {
+        for poll in &created[..votes + 1] {
+            cast_vote(
+                &state,
+                &poll.id,
+                Uuid::new_v4(),
+                &PollAccess::default(),
+                Ballot::new(vec![poll.options[0].id.clone()]),
+            )
+            .await
+            .unwrap();
+        }
+    }
+    server
+        .post(&format!("/api/polls/{}/close", created[3].id))
+        .add_header("Cookie", bob.clone())
+        .await;
+
+    // Walk every page of two, newest first
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = server.get("/api/polls").add_query_param("limit", 2);
+        if let Some(cursor) = &cursor {
+            request = request.add_query_param("cursor", cursor);
+        }
+        let page: PollPage = request.await.json();
+        assert!(page.items.len() <= 2);
+        seen.extend(page.items.into_iter().map(|poll| poll.id));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    let newest: Vec<String> = created.iter().rev().map(|poll| poll.id.clone()).collect();
+    assert_eq!(seen, newest);
+
+    let ids = |page: PollPage| -> Vec<String> { page.items.into_iter().map(|p| p.id).collect() };
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("sort", "most_votes")
+        .add_query_param("limit", 3)
+        .await
+        .json();
+    assert!(page.next_cursor.is_some());
+    assert_eq!(
+        ids(page),
+        vec![
+            created[0].id.clone(),
+            created[1].id.clone(),
+            created[2].id.clone()
+        ]
+    );
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("sort", "closing_soon")
+        .add_query_param("limit", 2)
+        .await
+        .json();
+    assert_eq!(
+        ids(page),
+        vec![created[1].id.clone(), created[0].id.clone()]
+    );
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("q", "LUNCH")
+        .add_query_param("creator", "bob")
+        .await
+        .json();
+    assert_eq!(ids(page), vec![created[1].id.clone()]);
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("status", "closed")
+        .await
+        .json();
+    assert_eq!(ids(page), vec![created[3].id.clone()]);
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("status", "open")
+        .add_query_param("created_after", created[2].created_at.to_rfc3339())
+        .await
+        .json();
+    assert_eq!(ids(page), vec![created[4].id.clone()]);
+
+    // A cursor only continues the order it was issued for
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("limit", 1)
+        .await
+        .json();
+    let response = server
+        .get("/api/polls")
+        .add_query_param("sort", "most_votes")
+        .add_query_param("cursor", page.next_cursor.unwrap())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["details"]["fields"][0]["field"], "cursor");
+
+    let response = server.get("/api/polls").add_query_param("limit", 500).await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_malformed_requests_use_the_error_shape() {
+    let server = create_memory_test_server();
+    let auth_token = authenticate_user(&server, "test_user").await;
+
+    let response = server
+        .get("/api/polls")
+        .add_query_param("sort", "bogus")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["details"]["fields"][0]["field"], "query");
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .content_type("application/json")
+        .text("{\"title\": ")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["details"]["fields"][0]["field"], "body");
+
+    let response = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "title": "No Options" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "validation_failed"
+    );
+}
+
+// Ids of every poll by `creator`, walking pages of one in `sort` order.
+async fn walk_pages(server: &TestServer, creator: &str, sort: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = server
+            .get("/api/polls")
+            .add_query_param("creator", creator)
+            .add_query_param("sort", sort)
+            .add_query_param("limit", 1);
+        if let Some(cursor) = &cursor {
+            request = request.add_query_param("cursor", cursor);
+        }
+        let page: PollPage = request.await.json();
+        seen.extend(page.items.into_iter().map(|poll| poll.id));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return seen,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mysql_listing_pages_with_cursors() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    set_test_env();
+    let state = AppState::mysql(pool);
+    let server = create_test_server(state.clone());
+    let creator = format!("pager-{}", Uuid::new_v4());
+    let auth_token = authenticate_user(&server, &creator).await;
+
+    let mut created = Vec::new();
+    for (title, votes) in [("First", 0), ("Second", 2), ("Third", 1)] {
+        let poll: Poll = server
+            .post("/api/polls")
+            .add_header("Cookie", auth_token.clone())
+            .json(&json!({ "title": title, "options": ["Yes", "No"] }))
+            .await
+            .json();
+        for _ in 0..votes {
+            cast_vote(
+                &state,
+                &poll.id,
+                Uuid::new_v4(),
+                &PollAccess::default(),
+                Ballot::new(vec![poll.options[0].id.clone()]),
+            )
+            .await
+            .unwrap();
+        }
+        created.push(poll.id);
+    }
+
+    let [first, second, third] = [0, 1, 2].map(|i| created[i].clone());
+    assert_eq!(
+        walk_pages(&server, &creator, "newest").await,
+        vec![third.clone(), second.clone(), first.clone()]
+    );
+    assert_eq!(
+        walk_pages(&server, &creator, "most_votes").await,
+        vec![second, third, first]
+    );
+}
+
+#[tokio::test]
+async fn test_most_votes_listing_keeps_hidden_counts_hidden() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+
+    let mut created = Vec::new();
+    for (title, results_visibility, votes) in [
+        ("Shown", "always", 1),
+        ("Secret", "after_close", 3),
+        ("Quiet", "after_close", 2),
+    ] {
+        let poll: Poll = server
+            .post("/api/polls")
+            .add_header("Cookie", alice.clone())
+            .json(&json!({
+                "title": title,
+                "options": ["Yes", "No"],
+                "results_visibility": results_visibility
+            }))
+            .await
+            .json();
+        for _ in 0..votes {
+            cast_vote(
+                &state,
+                &poll.id,
+                Uuid::new_v4(),
+                &PollAccess::default(),
+                Ballot::new(vec![poll.options[0].id.clone()]),
+            )
+            .await
+            .unwrap();
+        }
+        created.push(poll);
+    }
+
+    // Everyone else sees hidden polls as having no votes, in order and in
+    // the cursors
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = server
+            .get("/api/polls")
+            .add_query_param("sort", "most_votes")
+            .add_query_param("limit", 1);
+        if let Some(cursor) = &cursor {
+            request = request.add_query_param("cursor", cursor);
+        }
+        let page: PollPage = request.await.json();
+        let poll = &page.items[0];
+        seen.push(poll.id.clone());
+        let Some(next) = page.next_cursor else {
+            break;
+        };
+        let decoded = PollCursor::decode(&next).unwrap();
+        assert_eq!(decoded.key, -i64::from(poll.total_votes));
+        cursor = Some(next);
+    }
+    let mut hidden = vec![created[1].id.clone(), created[2].id.clone()];
+    hidden.sort();
+    assert_eq!(seen[0], created[0].id);
+    assert_eq!(seen[1..], hidden);
+
+    // The creator sees the real counts
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_header("Cookie", alice)
+        .add_query_param("sort", "most_votes")
+        .await
+        .json();
+    let ids: Vec<_> = page.items.into_iter().map(|poll| poll.id).collect();
+    assert_eq!(
+        ids,
+        [
+            created[1].id.clone(),
+            created[2].id.clone(),
+            created[0].id.clone()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_my_polls_and_votes() {
+    let server = create_memory_test_server();
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+
+    let create = |token: &String, body: serde_json::Value| {
+        server
+            .post("/api/polls")
+            .add_header("Cookie", token.clone())
+            .json(&body)
+    };
+    let secret: Poll = create(
+        &alice,
+        json!({ "title": "Secret", "options": ["A", "B"], "ballot_privacy": "anonymous" }),
+    )
+    .await
+    .json();
+    let ignored: Poll = create(&alice, json!({ "title": "Ignored", "options": ["A", "B"] }))
+        .await
+        .json();
+    let approval: Poll = create(
+        &bob,
+        json!({ "title": "Toppings", "options": ["Ham", "Olives", "Corn"], "voting_method": "approval" }),
+    )
+    .await
+    .json();
+
+    let mine: PollPage = server
+        .get("/api/me/polls")
+        .add_header("Cookie", alice.clone())
+        .add_query_param("creator", "bob")
+        .await
+        .json();
+    let mine: Vec<String> = mine.items.into_iter().map(|poll| poll.id).collect();
+    assert_eq!(mine, vec![ignored.id.clone(), secret.id.clone()]);
+
+    for (poll, body) in [
+        (&secret, json!({ "option_id": secret.options[1].id })),
+        (
+            &approval,
+            json!({ "option_ids": [approval.options[2].id, approval.options[0].id] }),
+        ),
+    ] {
+        let response = server
+            .post(&format!("/api/polls/{}/vote", poll.id))
+            .add_header("Cookie", alice.clone())
+            .json(&body)
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    let voted: Vec<VotedPoll> = server
+        .get("/api/me/votes")
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(voted.len(), 2);
+    assert_eq!(voted[0].poll.id, approval.id);
+    assert_eq!(
+        voted[0].my_ballot,
+        Some(Ballot::new(vec![
+            approval.options[0].id.clone(),
+            approval.options[2].id.clone()
+        ]))
+    );
+    assert_eq!(voted[1].poll.id, secret.id);
+    assert_eq!(voted[1].my_ballot, None);
+
+    let voted: Vec<VotedPoll> = server
+        .get("/api/me/votes")
+        .add_header("Cookie", bob.clone())
+        .await
+        .json();
+    assert!(voted.is_empty());
+
+    for path in ["/api/me/polls", "/api/me/votes"] {
+        let response = server.get(path).await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn test_mysql_reordered_options_are_fetched_in_order() {
+    let Some(server) = create_db_test_server().await else {
+        return;
+    };
+    let auth_token = authenticate_user(&server, "editor").await;
+    let voter = authenticate_user(&server, "voter").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({ "title": "Shuffle", "options": ["A", "B", "C"] }))
+        .await
+        .json();
+    let path = format!("/api/polls/{}", poll.id);
+    let [a, b, c] = [0, 1, 2].map(|i| poll.options[i].id.clone());
+
+    // Swapping positions must not trip over the per-poll position key
+    let edited: Poll = server
+        .patch(&path)
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Shuffled",
+            "options": [{ "id": c, "text": "C" }, { "text": "D" }, { "id": a, "text": "A" }]
+        }))
+        .await
+        .json();
+    let fetched: Poll = server.get(&path).await.json();
+    assert_eq!(fetched.title, "Shuffled");
+    assert_eq!(fetched.options, edited.options);
+    let texts: Vec<&str> = fetched
+        .options
+        .iter()
+        .map(|opt| opt.text.as_str())
+        .collect();
+    assert_eq!(texts, ["C", "D", "A"]);
+    assert!(fetched.options.iter().all(|opt| opt.id != b));
+
+    server
+        .post(&format!("{path}/vote"))
+        .add_header("Cookie", voter)
+        .json(&json!({ "option_id": a }))
+        .await;
+    let mut options: Vec<serde_json::Value> = fetched
+        .options
+        .iter()
+        .map(|opt| json!({ "id": opt.id, "text": opt.text }))
+        .collect();
+    options.push(json!({ "text": "E" }));
+    server
+        .patch(&path)
+        .add_header("Cookie", auth_token)
+        .json(&json!({ "options": options }))
+        .await;
+
+    let fetched: Poll = server.get(&path).await.json();
+    let options: Vec<(&str, i32)> = fetched
+        .options
+        .iter()
+        .map(|opt| (opt.text.as_str(), opt.votes))
+        .collect();
+    assert_eq!(options, [("C", 0), ("D", 0), ("A", 1), ("E", 0)]);
+}
+
+#[tokio::test]
+async fn test_edit_poll_before_and_after_votes() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "title": "Favourite colur", "options": ["Red", "Gren", "Blue"] }))
+        .await
+        .json();
+    let path = format!("/api/polls/{}", poll.id);
+    let [red, green, blue] = [0, 1, 2].map(|i| poll.options[i].id.clone());
+
+    let response = server
+        .patch(&path)
+        .add_header("Cookie", bob.clone())
+        .json(&json!({ "title": "Mine now" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let response = server
+        .patch(&path)
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "options": [{ "id": "nope", "text": "Red" }, { "text": "Pink" }] }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+    let response = server
+        .patch(&path)
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "options": [{ "id": red, "text": "Red" }, { "text": " red " }] }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Before any vote: retitle, rename, reorder, remove and add
+    let mut updates = state.poll_updates.subscribe();
+    let edited: Poll = server
+        .patch(&path)
+        .add_header("Cookie", alice.clone())
+        .json(&json!({
+            "title": "Favourite colour",
+            "options": [
+                { "id": green, "text": "Green" },
+                { "text": "Yellow" },
+                { "id": red, "text": "Red" }
+            ]
+        }))
+        .await
+        .json();
+    assert_eq!(edited.id, poll.id);
+    assert_eq!(edited.title, "Favourite colour");
+    let texts: Vec<&str> = edited.options.iter().map(|opt| opt.text.as_str()).collect();
+    assert_eq!(texts, ["Green", "Yellow", "Red"]);
+    assert_eq!(edited.options[0].id, green);
+    assert_eq!(edited.options[2].id, red);
+    assert!(edited.options.iter().all(|opt| opt.id != blue));
+    let PollEvent::Updated(broadcast) = updates.recv().await.unwrap() else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(*broadcast, edited);
+
+    server
+        .post(&format!("{path}/vote"))
+        .add_header("Cookie", bob.clone())
+        .json(&json!({ "option_id": red }))
+        .await;
+
+    // After the first vote only appending is allowed
+    for body in [
+        json!({ "title": "Something else" }),
+        json!({ "options": [{ "id": green, "text": "Green" }, { "id": red, "text": "Red" }] }),
+        json!({ "options": [
+            { "id": red, "text": "Red" },
+            { "id": green, "text": "Green" },
+            { "id": edited.options[1].id, "text": "Yellow" }
+        ] }),
+    ] {
+        let response = server
+            .patch(&path)
+            .add_header("Cookie", alice.clone())
+            .json(&body)
+            .await;
+        assert_eq!(response.status_code(), StatusCode::CONFLICT);
+        assert_eq!(
+            response.json::<serde_json::Value>()["code"],
+            "poll_has_votes"
+        );
+    }
+
+    let mut options: Vec<serde_json::Value> = edited
+        .options
+        .iter()
+        .map(|opt| json!({ "id": opt.id, "text": opt.text }))
+        .collect();
+    options.push(json!({ "text": "Purple" }));
+    let appended: Poll = server
+        .patch(&path)
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "title": "Favourite colour", "options": options }))
+        .await
+        .json();
+    assert_eq!(appended.options.len(), 4);
+    assert_eq!(appended.options[3].text, "Purple");
+    assert_eq!(appended.options[2].votes, 1);
+    assert_eq!(appended.total_votes, 1);
+
+    let fetched: Poll = server.get(&path).await.json();
+    assert_eq!(fetched, appended);
+}
+
+#[tokio::test]
+async fn test_mysql_scheduled_polls_open_and_close() {
+    let Some(pool) = connect_test_db().await else {
+        return;
+    };
+    set_test_env();
+    let state = AppState::mysql(pool);
+    let server = create_test_server(state.clone());
+    let auth_token = authenticate_user(&server, "scheduler").await;
+
+    let now = Utc::now();
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", auth_token.clone())
+        .json(&json!({
+            "title": "Later",
+            "options": ["Option 1", "Option 2"],
+            "opens_at": now + Duration::hours(1),
+            "closes_at": now + Duration::hours(2)
+        }))
+        .await
+        .json();
+    assert_eq!(poll.state, PollState::Scheduled);
+
+    let opened = state.polls.open_scheduled(now).await.unwrap();
+    assert!(!opened.contains(&poll.id));
+    let opened = state
+        .polls
+        .open_scheduled(now + Duration::minutes(90))
+        .await
+        .unwrap();
+    assert!(opened.contains(&poll.id));
+    let fetched = state.polls.get_poll(&poll.id).await.unwrap().unwrap();
+    assert_eq!(fetched.state, PollState::Open);
+    assert!(!fetched.is_closed);
+
+    let closed = state
+        .polls
+        .close_expired(now + Duration::hours(3))
+        .await
+        .unwrap();
+    assert!(closed.contains(&poll.id));
+    let fetched = state.polls.get_poll(&poll.id).await.unwrap().unwrap();
+    assert_eq!(fetched.state, PollState::Closed);
+    assert!(fetched.is_closed);
+    assert_eq!(fetched.closed_at, fetched.closes_at);
+    assert_eq!(fetched.closed_by, None);
+
+    // State changes only apply to a poll still in the expected state
+    let mut reopened = fetched.clone();
+    reopened.state = PollState::Open;
+    reopened.is_closed = false;
+    reopened.closed_at = None;
+    reopened.closes_at = None;
+    assert!(!state
+        .polls
+        .set_state(&reopened, PollState::Open)
+        .await
+        .unwrap());
+    assert!(state
+        .polls
+        .set_state(&reopened, PollState::Closed)
+        .await
+        .unwrap());
+    let fetched = state.polls.get_poll(&poll.id).await.unwrap().unwrap();
+    assert_eq!(fetched.state, PollState::Open);
+    assert!(!fetched.is_closed);
+    assert_eq!(fetched.closes_at, None);
+}
+
+#[tokio::test]
+async fn test_poll_lifecycle_transitions() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({
+            "title": "Soon",
+            "options": ["Option 1", "Option 2"],
+            "opens_at": Utc::now() + Duration::milliseconds(200)
+        }))
+        .await
+        .json();
+    assert_eq!(poll.state, PollState::Scheduled);
+    let path = format!("/api/polls/{}", poll.id);
+
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let mut updates = state.poll_updates.subscribe();
+    open_scheduled_now(&state).await;
+    let PollEvent::Updated(opened) = updates.recv().await.unwrap() else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(opened.state, PollState::Open);
+
+    let response = server
+        .post(&format!("{path}/close"))
+        .add_header("Cookie", bob.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let closed: Poll = server
+        .post(&format!("{path}/close"))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(closed.state, PollState::Closed);
+    assert!(closed.is_closed);
+    assert_eq!(closed.closed_by.as_deref(), Some("alice"));
+    assert!(closed.closed_at.is_some());
+
+    let response = server
+        .post(&format!("{path}/close"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["code"], "invalid_transition");
+    assert_eq!(body["details"]["from"], "closed");
+    assert_eq!(body["details"]["to"], "closed");
+
+    let response = server
+        .post(&format!("{path}/reopen"))
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "closes_at": Utc::now() - Duration::hours(1) }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let reopened: Poll = server
+        .post(&format!("{path}/reopen"))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(reopened.state, PollState::Open);
+    assert!(!reopened.is_closed);
+    assert_eq!(reopened.closed_at, None);
+    assert_eq!(reopened.closed_by, None);
+    let response = server
+        .post(&format!("{path}/vote"))
+        .add_header("Cookie", bob.clone())
+        .json(&json!({ "option_id": poll.options[0].id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // Archiving needs a closed poll and is final
+    let response = server
+        .post(&format!("{path}/archive"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    server
+        .post(&format!("{path}/close"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    let archived: Poll = server
+        .post(&format!("{path}/archive"))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(archived.state, PollState::Archived);
+    assert!(archived.is_closed);
+    assert_eq!(archived.closed_by.as_deref(), Some("alice"));
+    let response = server
+        .post(&format!("{path}/reopen"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+
+    let page: PollPage = server
+        .get("/api/polls")
+        .add_query_param("status", "archived")
+        .await
+        .json();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, poll.id);
+
+    // Polls closing on schedule have no closer, and reopen without a deadline
+    let closes_at = Utc::now() + Duration::milliseconds(200);
+    let expiring: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({
+            "title": "Quick",
+            "options": ["Option 1", "Option 2"],
+            "closes_at": closes_at
+        }))
+        .await
+        .json();
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    close_expired_now(&state).await;
+    let expired: Poll = server
+        .get(&format!("/api/polls/{}", expiring.id))
+        .await
+        .json();
+    assert_eq!(expired.state, PollState::Closed);
+    assert_eq!(expired.closed_by, None);
+    assert_eq!(expired.closed_at, expiring.closes_at);
+
+    let reopened: Poll = server
+        .post(&format!("/api/polls/{}/reopen", expiring.id))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(reopened.state, PollState::Open);
+    assert_eq!(reopened.closes_at, None);
+    close_expired_now(&state).await;
+    let still_open: Poll = server
+        .get(&format!("/api/polls/{}", expiring.id))
+        .await
+        .json();
+    assert!(!still_open.is_closed);
+}
+
+#[tokio::test]
+async fn test_draft_polls_stay_private_until_published() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+
+    let draft: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "title": "Work in progress", "options": ["A", "B"], "draft": true }))
+        .await
+        .json();
+    assert_eq!(draft.state, PollState::Draft);
+    let path = format!("/api/polls/{}", draft.id);
+
+    // Only the creator can see it
+    let preview: Poll = server
+        .get(&path)
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(preview.id, draft.id);
+    for request in [
+        server.get(&path).add_header("Cookie", bob.clone()),
+        server.get(&path),
+        server.get(&format!("{path}/results")),
+    ] {
+        assert_eq!(request.await.status_code(), StatusCode::NOT_FOUND);
+    }
+    let listed = |page: PollPage| page.items.iter().any(|poll| poll.id == draft.id);
+    assert!(!listed(server.get("/api/polls").await.json()));
+    assert!(!listed(
+        server
+            .get("/api/polls")
+            .add_header("Cookie", bob.clone())
+            .await
+            .json()
+    ));
+    assert!(listed(
+        server
+            .get("/api/polls")
+            .add_header("Cookie", alice.clone())
+            .await
+            .json()
+    ));
+    assert!(listed(
+        server
+            .get("/api/me/polls")
+            .add_header("Cookie", alice.clone())
+            .await
+            .json()
+    ));
+
+    // No votes through either path, and no broadcasts to anonymous sockets.
+    // Only the creator learns that the draft exists but is not open yet.
+    for (token, status, code) in [
+        (&bob, StatusCode::NOT_FOUND, "poll_not_found"),
+        (&alice, StatusCode::CONFLICT, "poll_not_open"),
+    ] {
+        let response = server
+            .post(&format!("{path}/vote"))
+            .add_header("Cookie", token.clone())
+            .json(&json!({ "option_id": draft.options[0].id }))
+            .await;
+        assert_eq!(response.status_code(), status);
+        assert_eq!(response.json::<serde_json::Value>()["code"], code);
+    }
+    let result = cast_vote(
+        &state,
+        &draft.id,
+        Uuid::new_v4(),
+        &PollAccess::default(),
+        Ballot::new(vec![draft.options[0].id.clone()]),
+    )
+    .await;
+    assert!(matches!(result, Err(ApiError::PollNotFound(_))));
+    assert!(WsMessage::from_event(
+        PollEvent::Updated(Box::new(draft.clone())),
+        &PollAccess::default()
+    )
+    .is_none());
+
+    // Managing it as someone else looks just like a missing poll
+    let mut requests = vec![
+        server.patch(&path).json(&json!({ "title": "Hijacked" })),
+        server.delete(&path),
+        server.get(&format!("{path}/voters")),
+    ];
+    for action in ["publish", "close", "reset", "share_token"] {
+        requests.push(server.post(&format!("{path}/{action}")));
+    }
+    for request in requests {
+        let response = request.add_header("Cookie", bob.clone()).await;
+        assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.json::<serde_json::Value>()["code"],
+            "poll_not_found"
+        );
+    }
+
+    let mut updates = state.poll_updates.subscribe();
+    let published: Poll = server
+        .post(&format!("{path}/publish"))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    assert_eq!(published.state, PollState::Open);
+    let PollEvent::Updated(broadcast) = updates.recv().await.unwrap() else {
+        panic!("expected a poll update");
+    };
+    assert!(WsMessage::from_event(PollEvent::Updated(broadcast), &PollAccess::default()).is_some());
+
+    let response = server
+        .post(&format!("{path}/publish"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+
+    // Once visible, it is merely not bob's to manage
+    let response = server
+        .post(&format!("{path}/close"))
+        .add_header("Cookie", bob.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    assert!(listed(server.get("/api/polls").await.json()));
+    let response = server
+        .post(&format!("{path}/vote"))
+        .add_header("Cookie", bob.clone())
+        .json(&json!({ "option_id": draft.options[0].id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_private_polls_need_token_or_invite() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let bob = authenticate_user(&server, "bob").await;
+    let carol = authenticate_user(&server, "carol").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({
+            "title": "Team only",
+            "options": ["A", "B"],
+            "visibility": "private",
+            "allowed_users": [" carol ", "carol", ""]
+        }))
+        .await
+        .json();
+    assert_eq!(poll.visibility, PollVisibility::Private);
+    assert_eq!(poll.allowed_users, ["carol"]);
+    let token = poll.share_token.clone().unwrap();
+    assert_eq!(token.len(), 64);
+    let path = format!("/api/polls/{}", poll.id);
+
+    let listed = |page: PollPage| page.items.iter().any(|listed| listed.id == poll.id);
+    assert!(!listed(server.get("/api/polls").await.json()));
+    assert!(!listed(
+        server
+            .get("/api/polls")
+            .add_header("Cookie", carol.clone())
+            .await
+            .json()
+    ));
+    assert!(listed(
+        server
+            .get("/api/polls")
+            .add_header("Cookie", alice.clone())
+            .await
+            .json()
+    ));
+
+    // Strangers need the token; invited users do not
+    let response = server.get(&path).add_header("Cookie", bob.clone()).await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let response = server
+        .get(&path)
+        .add_query_param("token", "guess")
+        .add_header("Cookie", bob.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let shared = server
+        .get(&path)
+        .add_query_param("token", &token)
+        .add_header("Cookie", bob.clone())
+        .await
+        .json::<serde_json::Value>();
+    assert_eq!(shared["id"], poll.id);
+    assert!(shared.get("share_token").is_none());
+    assert!(shared.get("allowed_users").is_none());
+    let response = server.get(&path).add_header("Cookie", carol.clone()).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let vote = json!({ "option_id": poll.options[0].id });
+    let response = server
+        .post(&format!("{path}/vote"))
+        .add_header("Cookie", bob.clone())
+        .json(&vote)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let response = server
+        .post(&format!("{path}/vote"))
+        .add_query_param("token", &token)
+        .add_header("Cookie", bob.clone())
+        .json(&vote)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let response = server
+        .get(&format!("{path}/results"))
+        .add_query_param("token", &token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // The websocket vote path and broadcasts check the same access
+    let stranger = PollAccess::default();
+    let with_token = PollAccess::new(None, Some(token.clone()));
+    let ballot = Ballot::new(vec![poll.options[1].id.clone()]);
+    let result = cast_vote(&state, &poll.id, Uuid::new_v4(), &stranger, ballot.clone()).await;
+    assert!(matches!(result, Err(ApiError::PollNotFound(_))));
+    cast_vote(&state, &poll.id, Uuid::new_v4(), &with_token, ballot)
+        .await
+        .unwrap();
+    let event = || PollEvent::Updated(Box::new(poll.clone()));
+    assert!(WsMessage::from_event(event(), &stranger).is_none());
+    let Some(WsMessage::PollUpdate { poll: pushed }) = WsMessage::from_event(event(), &with_token)
+    else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(pushed.share_token, None);
+
+    // Sockets following every poll don't learn of private ones, even
+    // deleted
+    let mut everything = Subscriptions::new(None);
+    everything.set_all(true);
+    let deleted = |poll: &Poll| PollEvent::Deleted(Box::new(poll.clone()));
+    assert!(everything.forward(deleted(&poll)).is_none());
+    let mut invited = Subscriptions::new(Some("carol".to_string()));
+    invited.set_all(true);
+    let Some(WsMessage::PollDeleted { poll_id }) = invited.forward(deleted(&poll)) else {
+        panic!("expected a deletion");
+    };
+    assert_eq!(poll_id, poll.id);
+
+    // Rotating the token cuts off the old link. Only the creator may; to
+    // anyone who can't see the poll it doesn't exist
+    for (user, status) in [
+        (&bob, StatusCode::NOT_FOUND),
+        (&carol, StatusCode::FORBIDDEN),
+    ] {
+        let response = server
+            .post(&format!("{path}/share_token"))
+            .add_header("Cookie", user.clone())
+            .await;
+        assert_eq!(response.status_code(), status);
+    }
+    let rotated: Poll = server
+        .post(&format!("{path}/share_token"))
+        .add_header("Cookie", alice.clone())
+        .await
+        .json();
+    let new_token = rotated.share_token.unwrap();
+    assert_ne!(new_token, token);
+    let response = server.get(&path).add_query_param("token", &token).await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let response = server.get(&path).add_query_param("token", &new_token).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // Making it public lists it again
+    let public: Poll = server
+        .patch(&path)
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "visibility": "public" }))
+        .await
+        .json();
+    assert_eq!(public.visibility, PollVisibility::Public);
+    assert!(listed(server.get("/api/polls").await.json()));
+    let response = server
+        .post(&format!("{path}/share_token"))
+        .add_header("Cookie", alice.clone())
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_ws_following_all_hears_when_a_poll_goes_private() {
+    let server = create_memory_test_server();
+    let alice = authenticate_user(&server, "alice").await;
+
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "title": "Open For Now", "options": ["Yes", "No"] }))
+        .await
+        .json();
+    let updated = |poll: &Poll| PollEvent::Updated(Box::new(poll.clone()));
+
+    let mut watching = Subscriptions::new(None);
+    watching.set_all(true);
+    assert!(matches!(
+        watching.forward(updated(&poll)),
+        Some(WsMessage::PollUpdate { .. })
+    ));
+    let mut late = Subscriptions::new(None);
+    late.set_all(true);
+
+    let private: Poll = server
+        .patch(&format!("/api/polls/{}", poll.id))
+        .add_header("Cookie", alice)
+        .json(&json!({ "visibility": "private" }))
+        .await
+        .json();
+
+    // Only the socket that was sent the poll learns it is gone, once
+    let Some(WsMessage::PollDeleted { poll_id }) = watching.forward(updated(&private)) else {
+        panic!("expected a deletion");
+    };
+    assert_eq!(poll_id, poll.id);
+    assert!(watching.forward(updated(&private)).is_none());
+    assert!(late.forward(updated(&private)).is_none());
+}
+
+type WsClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Serves the whole app on a free port, as `main` does.
+async fn serve_app(state: AppState, sessions: MemoryStore) -> SocketAddr {
+    set_test_env();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = create_router(state, sessions);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// Opens a websocket on `path`, sending `headers` with the handshake.
+async fn open_ws(
+    addr: SocketAddr,
+    path: &str,
+    headers: &[(&'static str, &str)],
+) -> Result<WsClient, WsError> {
+    let mut request = format!("ws://{addr}{path}").into_client_request()?;
+    for (name, value) in headers {
+        request.headers_mut().insert(*name, value.parse().unwrap());
+    }
+    Ok(connect_async(request).await?.0)
+}
+
+/// Serves the app and connects to its websocket, sending `cookie` with the
+/// handshake.
+async fn connect_ws(state: AppState, sessions: MemoryStore, cookie: Option<&str>) -> WsClient {
+    let addr = serve_app(state, sessions).await;
+    let headers: Vec<_> = cookie
+        .map(|cookie| ("Cookie", cookie))
+        .into_iter()
+        .collect();
+    open_ws(addr, "/ws/polls", &headers).await.unwrap()
+}
+
+/// Stores a logged-in session for `username` and returns its cookie.
+async fn login_session(sessions: &MemoryStore, username: &str) -> (Uuid, String) {
+    let user_id = Uuid::new_v4();
+    let mut record = Record {
+        id: Id::default(),
+        data: [
+            ("user_id".to_string(), json!(user_id)),
+            ("username".to_string(), json!(username)),
+        ]
+        .into(),
+        expiry_date: OffsetDateTime::now_utc() + CookieDuration::minutes(5),
+    };
+    sessions.create(&mut record).await.unwrap();
+    (user_id, format!("other=1; webauthn={}", record.id))
+}
+
+async fn send_ws(client: &mut WsClient, message: serde_json::Value) {
+    client
+        .send(Message::Text(message.to_string().into()))
+        .await
+        .unwrap();
+}
+
+async fn next_ws(client: &mut WsClient) -> WsMessage {
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(2), client.next())
+        .await
+        .expect("no websocket message in time")
+        .unwrap()
+        .unwrap();
+    serde_json::from_str(frame.to_text().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_ws_forwards_only_subscribed_polls() {
+    let state = AppState::in_memory();
+    let request = |title: &str| {
+        serde_json::from_value(json!({ "title": title, "options": ["A", "B"] })).unwrap()
+    };
+    let watched = create_poll_as(&state, "alice".to_string(), request("Watched"))
+        .await
+        .unwrap();
+    let other = create_poll_as(&state, "alice".to_string(), request("Other"))
+        .await
+        .unwrap();
+    let vote = |poll: &Poll| {
+        let state = state.clone();
+        let ballot = Ballot::new(vec![poll.options[0].id.clone()]);
+        let poll_id = poll.id.clone();
+        async move {
+            cast_vote(
+                &state,
+                &poll_id,
+                Uuid::new_v4(),
+                &PollAccess::default(),
+                ballot,
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let mut client = connect_ws(state.clone(), MemoryStore::default(), None).await;
+
+    send_ws(
+        &mut client,
+        json!({ "type": "Subscribe", "poll_id": watched.id }),
+    )
+    .await;
+    let WsMessage::Subscribed { poll_id, .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    assert_eq!(poll_id.as_deref(), Some(watched.id.as_str()));
+    let WsMessage::PollUpdate { poll } = next_ws(&mut client).await else {
+        panic!("expected the current poll");
+    };
+    assert_eq!(poll.id, watched.id);
+
+    // Votes elsewhere are not forwarded
+    vote(&other).await;
+    vote(&watched).await;
+    let WsMessage::PollUpdate { poll } = next_ws(&mut client).await else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(
+        (poll.id.as_str(), poll.total_votes),
+        (watched.id.as_str(), 1)
+    );
+
+    send_ws(
+        &mut client,
+        json!({ "type": "Unsubscribe", "poll_id": watched.id }),
+    )
+    .await;
+    let WsMessage::Unsubscribed { poll_id, .. } = next_ws(&mut client).await else {
+        panic!("expected an unsubscribe ack");
+    };
+    assert_eq!(poll_id.as_deref(), Some(watched.id.as_str()));
+
+    send_ws(&mut client, json!({ "type": "SubscribeAll" })).await;
+    let WsMessage::Subscribed { poll_id: None, .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe-all ack");
+    };
+    vote(&other).await;
+    let WsMessage::PollUpdate { poll } = next_ws(&mut client).await else {
+        panic!("expected a poll update");
+    };
+    assert_eq!(poll.id, other.id);
+
+    send_ws(&mut client, json!({ "type": "UnsubscribeAll" })).await;
+    let WsMessage::Unsubscribed { poll_id: None, .. } = next_ws(&mut client).await else {
+        panic!("expected an unsubscribe-all ack");
+    };
+    vote(&other).await;
+    vote(&watched).await;
+    send_ws(
+        &mut client,
+        json!({ "type": "Subscribe", "poll_id": other.id }),
+    )
+    .await;
+    let WsMessage::Subscribed { poll_id, .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    assert_eq!(poll_id.as_deref(), Some(other.id.as_str()));
+}
+
+#[tokio::test]
+async fn test_ws_votes_need_a_session() {
+    let state = AppState::in_memory();
+    let sessions = MemoryStore::default();
+    let (user_id, cookie) = login_session(&sessions, "bob").await;
+    let (_, unknown) = login_session(&MemoryStore::default(), "bob").await;
+
+    let request = serde_json::from_value(json!({
+        "title": "Socket vote",
+        "options": ["A", "B"],
+        "visibility": "private",
+        "allowed_users": ["bob"]
+    }))
+    .unwrap();
+    let poll = create_poll_as(&state, "alice".to_string(), request)
+        .await
+        .unwrap();
+    let vote = json!({ "type": "Vote", "poll_id": poll.id, "option_id": poll.options[0].id });
+
+    // Sockets without a live session can't see the private poll, let alone
+    // vote in it
+    for cookie in [None, Some("webauthn=forged"), Some(unknown.as_str())] {
+        let mut anonymous = connect_ws(state.clone(), sessions.clone(), cookie).await;
+        send_ws(&mut anonymous, vote.clone()).await;
+        let WsMessage::Error { code, .. } = next_ws(&mut anonymous).await else {
+            panic!("expected an error");
+        };
+        assert_eq!(code, "not_authenticated");
+        let stored = state.polls.get_poll(&poll.id).await.unwrap().unwrap();
+        assert_eq!(stored.total_votes, 0);
+    }
+
+    // The invited user's socket votes as them, and sees the update
+    let mut client = connect_ws(state.clone(), sessions.clone(), Some(&cookie)).await;
+    send_ws(
+        &mut client,
+        json!({ "type": "Subscribe", "poll_id": poll.id }),
+    )
+    .await;
+    let WsMessage::Subscribed { .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    let WsMessage::PollUpdate { .. } = next_ws(&mut client).await else {
+        panic!("expected the current poll");
+    };
+    send_ws(&mut client, vote).await;
+    // The ack and the broadcast update are queued by different tasks
+    let (mut acked, mut updated) = (None, None);
+    for _ in 0..2 {
+        match next_ws(&mut client).await {
+            WsMessage::Voted { poll_id, .. } => acked = Some(poll_id),
+            WsMessage::PollUpdate { poll } => updated = Some(poll.total_votes),
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+    assert_eq!(acked, Some(poll.id.clone()));
+    assert_eq!(updated, Some(1));
+    let voters: Vec<_> = state
+        .polls
+        .attributed_ballots(&poll.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(voter, _)| voter)
+        .collect();
+    assert_eq!(voters, [user_id]);
+}
+
+#[tokio::test]
+async fn test_ws_drops_subscriptions_cut_off_by_a_new_token() {
+    set_test_env();
+    let state = AppState::in_memory();
+    let server = create_test_server(state.clone());
+    let alice = authenticate_user(&server, "alice").await;
+    let poll: Poll = server
+        .post("/api/polls")
+        .add_header("Cookie", alice.clone())
+        .json(&json!({ "title": "Shared link", "options": ["A", "B"], "visibility": "private" }))
+        .await
+        .json();
+    let token = poll.share_token.clone().unwrap();
+
+    let mut client = connect_ws(state.clone(), MemoryStore::default(), None).await;
+    send_ws(
+        &mut client,
+        json!({ "type": "SubscribeResults", "poll_id": poll.id, "token": token, "live": true }),
+    )
+    .await;
+    let WsMessage::Subscribed { .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    let WsMessage::Results { .. } = next_ws(&mut client).await else {
+        panic!("expected the current results");
+    };
+
+    // The old link stops working, and the socket is told so
+    server
+        .post(&format!("/api/polls/{}/share_token", poll.id))
+        .add_header("Cookie", alice.clone())
+        .await;
+    let WsMessage::PollDeleted { poll_id } = next_ws(&mut client).await else {
+        panic!("expected the poll to be revoked");
+    };
+    assert_eq!(poll_id, poll.id);
+
+    // Later updates and results no longer reach it
+    server
+        .post(&format!("/api/polls/{}/vote", poll.id))
+        .add_header("Cookie", alice)
+        .json(&json!({ "option_id": poll.options[0].id }))
+        .await;
+    send_ws(
+        &mut client,
+        json!({ "type": "Subscribe", "poll_id": poll.id, "token": token }),
+    )
+    .await;
+    let WsMessage::Error { code, .. } = next_ws(&mut client).await else {
+        panic!("expected an error");
+    };
+    assert_eq!(code, "poll_not_found");
+}
+
+#[tokio::test]
+async fn test_ws_endpoint_shares_the_http_checks() {
+    let state = AppState::in_memory();
+    let sessions = MemoryStore::default();
+    let addr = serve_app(state.clone(), sessions).await;
+    let request = serde_json::from_value(json!({
+        "title": "Shared link",
+        "options": ["A", "B"],
+        "visibility": "private"
+    }))
+    .unwrap();
+    let poll = create_poll_as(&state, "alice".to_string(), request)
+        .await
+        .unwrap();
+    let token = poll.share_token.clone().unwrap();
+    let status = |result: Result<WsClient, WsError>| match result {
+        Err(WsError::Http(response)) => response.status(),
+        Err(e) => panic!("unexpected handshake error: {e}"),
+        Ok(_) => StatusCode::SWITCHING_PROTOCOLS,
+    };
+
+    // Browsers on other sites can't open sockets
+    let foreign = [("Origin", "https://elsewhere.example")];
+    let result = open_ws(addr, "/ws/polls", &foreign).await;
+    assert_eq!(status(result), StatusCode::FORBIDDEN);
+    let frontend = [("Origin", "http://localhost:3000")];
+    let result = open_ws(addr, "/ws/polls", &frontend).await;
+    assert_eq!(status(result), StatusCode::SWITCHING_PROTOCOLS);
+
+    // The per-poll path checks access, then subscribes
+    let path = format!("/ws/polls/{}", poll.id);
+    let result = open_ws(addr, &path, &[]).await;
+    assert_eq!(status(result), StatusCode::NOT_FOUND);
+    let mut client = open_ws(addr, &format!("{path}?token={token}"), &[])
+        .await
+        .unwrap();
+    let WsMessage::Subscribed { poll_id, .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    assert_eq!(poll_id.as_deref(), Some(poll.id.as_str()));
+    let WsMessage::PollUpdate { poll: current } = next_ws(&mut client).await else {
+        panic!("expected the current poll");
+    };
+    assert_eq!(current.share_token, None);
+
+    // Live results follow every vote
+    send_ws(
+        &mut client,
+        json!({ "type": "SubscribeResults", "poll_id": poll.id, "token": token, "live": true }),
+    )
+    .await;
+    let WsMessage::Subscribed { .. } = next_ws(&mut client).await else {
+        panic!("expected a subscribe ack");
+    };
+    let WsMessage::Results { results, .. } = next_ws(&mut client).await else {
+        panic!("expected results");
+    };
+    assert_eq!(results.total_votes, 0);
+    let ballot = Ballot::new(vec![poll.options[1].id.clone()]);
+    let access = PollAccess::new(None, Some(token));
+    cast_vote(&state, &poll.id, Uuid::new_v4(), &access, ballot)
+        .await
+        .unwrap();
+    let WsMessage::PollUpdate { .. } = next_ws(&mut client).await else {
+        panic!("expected a poll update");
+    };
+    let WsMessage::Results { results, .. } = next_ws(&mut client).await else {
+        panic!("expected results");
+    };
+    assert_eq!(results.total_votes, 1);
+    assert_eq!(results.leaders, [poll.options[1].id.clone()]);
+}
+
+/// Frames a client could plausibly send, used as seeds for the parser tests.
+fn sample_frames() -> Vec<serde_json::Value> {
+    vec![
+        json!({ "type": "Subscribe", "poll_id": "p1" }),
+        json!({ "type": "Subscribe", "poll_id": "p1", "token": "t", "request_id": "r1" }),
+        json!({ "type": "Unsubscribe", "poll_id": "p1", "request_id": "r2" }),
+        json!({ "type": "SubscribeAll" }),
+        json!({ "type": "UnsubscribeAll", "request_id": "r3" }),
+        json!({ "type": "SubscribeResults", "poll_id": "p1", "live": true }),
+        json!({ "type": "Vote", "poll_id": "p1", "option_id": "o1", "request_id": "r4" }),
+        json!({ "type": "Vote", "poll_id": "p1", "option_ids": ["o2", "o1"] }),
+        json!({ "type": "Vote", "poll_id": "p1", "scores": { "o1": 3, "o2": 0 } }),
+    ]
+}
+
+const PARSER_ERROR_CODES: [&str; 3] = ["invalid_json", "invalid_message", "unsupported_message"];
+
+#[test]
+fn test_ws_parser_accepts_commands_and_echoes_request_ids() {
+    for frame in sample_frames() {
+        let command = ClientCommand::parse(&frame.to_string()).unwrap();
+        assert_eq!(command.request_id.as_deref(), frame["request_id"].as_str());
+        // Parsing is lossless: the command serializes back to the frame
+        let mut reparsed = serde_json::to_value(&command.message).unwrap();
+        if let Some(request_id) = &command.request_id {
+            reparsed["request_id"] = json!(request_id);
+        }
+        assert_eq!(reparsed, frame);
+    }
+
+    let refused = |frame: &str| ClientCommand::parse(frame).unwrap_err();
+    let error = refused("{\"type\": \"Subscribe\"");
+    assert_eq!(
+        (error.code.as_str(), error.request_id),
+        ("invalid_json", None)
+    );
+    let error = refused(r#"{"type": "Dance", "request_id": "r1"}"#);
+    assert_eq!(error.code, "invalid_message");
+    assert_eq!(error.request_id.as_deref(), Some("r1"));
+    let error = refused(r#"{"type": "Vote", "option_id": "o1", "request_id": 7}"#);
+    assert_eq!(
+        (error.code.as_str(), error.request_id),
+        ("invalid_message", None)
+    );
+    let error = refused(r#"{"type": "PollDeleted", "poll_id": "p1", "request_id": "r2"}"#);
+    assert_eq!(error.code, "unsupported_message");
+    assert_eq!(error.request_id.as_deref(), Some("r2"));
+    for frame in [
+        "",
+        "null",
+        "[]",
+        "42",
+        "\"Subscribe\"",
+        "{}",
+        &"[".repeat(10_000),
+    ] {
+        assert!(PARSER_ERROR_CODES.contains(&refused(frame).code.as_str()));
+    }
+}
+
+#[test]
+fn test_ws_parser_survives_random_and_mutated_frames() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let seeds: Vec<String> = sample_frames().iter().map(|f| f.to_string()).collect();
+    let alphabet: Vec<char> = "{}[]\":,\\ -0123456789.eE+truefalsnl\u{0}\u{7f}é😀"
+        .chars()
+        .collect();
+
+    for _ in 0..2_000 {
+        // Arbitrary bytes, decoded however they happen to decode
+        let len = rng.gen_range(0..64);
+        let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        let frame = String::from_utf8_lossy(&bytes);
+        if let Err(e) = ClientCommand::parse(&frame) {
+            assert!(PARSER_ERROR_CODES.contains(&e.code.as_str()), "{e:?}");
+        }
+
+        // A valid command with a few characters inserted, removed or replaced
+        let mut chars: Vec<char> = seeds[rng.gen_range(0..seeds.len())].chars().collect();
+        for _ in 0..rng.gen_range(1..4) {
+            let at = rng.gen_range(0..=chars.len());
+            let c = alphabet[rng.gen_range(0..alphabet.len())];
+            match rng.gen_range(0..3) {
+                0 => chars.insert(at, c),
+                _ if at == chars.len() => chars.push(c),
+                1 => {
+                    chars.remove(at);
+                }
+                _ => chars[at] = c,
+            }
+        }
+        let frame: String = chars.into_iter().collect();
+        match ClientCommand::parse(&frame) {
+            Ok(command) => assert!(command.message.is_command()),
+            Err(e) => assert!(PARSER_ERROR_CODES.contains(&e.code.as_str()), "{e:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_ws_replies_with_errors_and_keeps_going() {
+    let state = AppState::in_memory();
+    let sessions = MemoryStore::default();
+    let (_, cookie) = login_session(&sessions, "bob").await;
+    let request =
+        serde_json::from_value(json!({ "title": "Errors", "options": ["A", "B"] })).unwrap();
+    let poll = create_poll_as(&state, "alice".to_string(), request)
+        .await
+        .unwrap();
+    let mut client = connect_ws(state.clone(), sessions, Some(&cookie)).await;
+    let error = |message: WsMessage| match message {
+        WsMessage::Error {
+            code, request_id, ..
+        } => (code, request_id),
+        other => panic!("expected an error, got {other:?}"),
+    };
+
+    client.send(Message::Text("{ nope".into())).await.unwrap();
+    assert_eq!(
+        error(next_ws(&mut client).await),
+        ("invalid_json".to_string(), None)
+    );
+    client
+        .send(Message::Binary(vec![1, 2, 3].into()))
+        .await
+        .unwrap();
+    assert_eq!(error(next_ws(&mut client).await).0, "binary_not_supported");
+    send_ws(
+        &mut client,
+        json!({ "type": "Subscribe", "poll_id": "missing", "request_id": "sub-1" }),
+    )
+    .await;
+    assert_eq!(
+        error(next_ws(&mut client).await),
+        ("poll_not_found".to_string(), Some("sub-1".to_string()))
+    );
+    send_ws(
+        &mut client,
+        json!({ "type": "Vote", "poll_id": poll.id, "option_id": "nope", "request_id": "vote-1" }),
+    )
+    .await;
+    assert_eq!(
+        error(next_ws(&mut client).await),
+        ("option_not_found".to_string(), Some("vote-1".to_string()))
+    );
+
+    // The socket still works, and acknowledgements carry the request id
+    send_ws(
+        &mut client,
+        json!({ "type": "Vote", "poll_id": poll.id, "option_id": poll.options[0].id, "request_id": "vote-2" }),
+    )
+    .await;
+    let WsMessage::Voted {
+        poll_id,
+        request_id,
+    } = next_ws(&mut client).await
+    else {
+        panic!("expected a vote ack");
+    };
+    assert_eq!(
+        (poll_id, request_id.as_deref()),
+        (poll.id.clone(), Some("vote-2"))
+    );
+    send_ws(
+        &mut client,
+        json!({ "type": "SubscribeAll", "request_id": "all-1" }),
+    )
+    .await;
+    let WsMessage::Subscribed {
+        poll_id: None,
+        request_id,
+    } = next_ws(&mut client).await
+    else {
+        panic!("expected a subscribe-all ack");
+    };
+    assert_eq!(request_id.as_deref(), Some("all-1"));
+
+    // Oversized frames and close frames end the connection
+    let mut large = connect_ws(state.clone(), MemoryStore::default(), None).await;
+    let padding = "x".repeat(MAX_MESSAGE_SIZE);
+    let _ = large
+        .send(Message::Text(
+            format!(r#"{{"type": "SubscribeAll", "pad": "{padding}"}}"#).into(),
+        ))
+        .await;
+    assert_ws_closed(&mut large).await;
+    client.send(Message::Close(None)).await.unwrap();
+    assert_ws_closed(&mut client).await;
+}
+
+async fn assert_ws_closed(client: &mut WsClient) {
+    let closed = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+        loop {
+            match client.next().await {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => break,
+                Some(Ok(_)) => continue,
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "websocket stayed open");
+}
+
+#[tokio::test]
+async fn test_ws_resyncs_clients_that_fall_behind() {
+    let state = AppState::in_memory();
+    let request = |title: &str| {
+        serde_json::from_value(json!({ "title": title, "options": ["A", "B"] })).unwrap()
+    };
+    let mut polls = Vec::new();
+    for title in ["Busy", "Doomed", "Quiet"] {
+        polls.push(
+            create_poll_as(&state, "alice".to_string(), request(title))
+                .await
+                .unwrap(),
+        );
+    }
+    let request = serde_json::from_value(
+        json!({ "title": "Shared", "options": ["A", "B"], "visibility": "private" }),
+    )
+    .unwrap();
+    let shared = create_poll_as(&state, "alice".to_string(), request)
+        .await
+        .unwrap();
+    let (busy, doomed) = (&polls[0], &polls[1]);
+    let mut client = connect_ws(state.clone(), MemoryStore::default(), None).await;
+    for poll in [busy, doomed, &shared] {
+        send_ws(
+            &mut client,
+            json!({ "type": "Subscribe", "poll_id": poll.id, "token": poll.share_token }),
+        )
+        .await;
+        let WsMessage::Subscribed { .. } = next_ws(&mut client).await else {
+            panic!("expected a subscribe ack");
+        };
+        let WsMessage::PollUpdate { .. } = next_ws(&mut client).await else {
+            panic!("expected the current poll");
+        };
+    }
+
+    // Nothing runs the connection while these are sent, so it misses most
+    // of them, the deletion included. Nor is it told the shared link changed
+    state
+        .polls
+        .set_share_token(&shared.id, "rotated")
+        .await
+        .unwrap();
+    state
+        .polls
+        .soft_delete_poll(&doomed.id, Utc::now())
+        .await
+        .unwrap();
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Deleted(Box::new(doomed.clone())));
+    for _ in 0..150 {
+        let _ = state
+            .poll_updates
+            .send(PollEvent::Updated(Box::new(busy.clone())));
+    }
+    let WsMessage::Resync {
+        polls: synced,
+        deleted,
+    } = next_ws(&mut client).await
+    else {
+        panic!("expected a resync");
+    };
+    let synced: Vec<_> = synced.iter().map(|poll| poll.id.as_str()).collect();
+    assert_eq!(synced, [busy.id.as_str()]);
+    let mut deleted = deleted;
+    deleted.sort();
+    let mut gone = vec![doomed.id.clone(), shared.id.clone()];
+    gone.sort();
+    assert_eq!(deleted, gone);
+
+    // The shared poll is no longer followed: only the deletion gets through
+    // after the updates still buffered
+    let mut rotated = shared.clone();
+    rotated.share_token = Some("rotated".to_string());
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Updated(Box::new(rotated)));
+    let _ = state
+        .poll_updates
+        .send(PollEvent::Deleted(Box::new(doomed.clone())));
+    loop {
+        match next_ws(&mut client).await {
+            WsMessage::PollUpdate { poll } => assert_eq!(poll.id, busy.id),
+            WsMessage::PollDeleted { poll_id } => {
+                assert_eq!(poll_id, doomed.id);
+                break;
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    // Following everything resyncs every listed poll
+    let mut all = connect_ws(state.clone(), MemoryStore::default(), None).await;
+    send_ws(&mut all, json!({ "type": "SubscribeAll" })).await;
+    let WsMessage::Subscribed { poll_id: None, .. } = next_ws(&mut all).await else {
+        panic!("expected a subscribe-all ack");
+    };
+    for _ in 0..150 {
+        let _ = state
+            .poll_updates
+            .send(PollEvent::Updated(Box::new(busy.clone())));
+    }
+    let WsMessage::Resync { polls: synced, .. } = next_ws(&mut all).await else {
+        panic!("expected a resync");
+    };
+    let mut synced: Vec<_> = synced.into_iter().map(|poll| poll.id).collect();
+    synced.sort();
+    let mut listed = vec![busy.id.clone(), polls[2].id.clone()];
+    listed.sort();
+    assert_eq!(synced, listed);
+}
+
+#[tokio::test]
+async fn test_ws_outbox_gives_up_on_slow_clients() {
+    let message = || WsMessage::PollDeleted {
+        poll_id: "gone".to_string(),
+    };
+    let (outbox, mut queued) = Outbox::new(2, std::time::Duration::from_millis(50));
+    assert!(outbox.send(message()).await);
+    assert!(outbox.send(message()).await);
+    // Full and nobody reading
+    assert!(!outbox.send(message()).await);
+
+    // Room again once the writer catches up
+    assert!(queued.recv().await.is_some());
+    assert!(outbox.send(message()).await);
+
+    // The writer is gone
+    drop(queued);
+    assert!(!outbox.send(message()).await);
+}
+
+#[tokio::test]
+async fn test_unauthorized_access() {
+    let app = create_test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    // Try to create poll without authentication
+    let response = server
+        .post("/api/polls")
+        .json(&json!({
+            "title": "Unauthorized Poll",
+            "options": ["Option 1", "Option 2"]
+        }))
+        .await;
+
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_cors_preflight() {
+    let app = create_test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let preflight = |origin: &'static str| {
+        server
+            .method(Method::OPTIONS, "/api/polls")
+            .add_header("Origin", origin)
+            .add_header("Access-Control-Request-Method", "POST")
+            .add_header("Access-Control-Request-Headers", "content-type")
+    };
+    let response = preflight("http://localhost:3000").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        response.header("access-control-allow-origin"),
+        "http://localhost:3000"
+    );
+    assert_eq!(response.header("access-control-allow-credentials"), "true");
+
+    let response = preflight("https://elsewhere.example").await;
+    assert!(response
+        .maybe_header("access-control-allow-origin")
+        .is_none());
+
+    // Actual responses carry the header too
+    let response = server
+        .get("/api/polls")
+        .add_header("Origin", "http://localhost:3000")
+        .await;
+    assert_eq!(
+        response.header("access-control-allow-origin"),
+        "http://localhost:3000"
+    );
+}
@@ -9,6 +9,7 @@ export class WebSocketService {
   private maxReconnectAttempts = 5;
   private reconnectTimeout = 1000;
   private isConnecting = false;
+  private subscribedToAll = false;
 
   private constructor() {
     this.connect();
@@ -35,7 +36,7 @@ export class WebSocketService {
       // Use WSS in production, WS in development
       const wsProtocol = isProduction ? "wss://" : "ws://";
       const wsBaseUrl =
-        process.env.NEXT_PUBLIC_WS_URL || "websocket.3.108.234.78.sslip.io";
+        process.env.NEXT_PUBLIC_WS_URL || "backend.3.108.234.78.sslip.io";
 
       // Remove any existing protocol
       const cleanWsUrl = wsBaseUrl.replace(/^(wss?:\/\/)/, "");
@@ -48,6 +49,9 @@ export class WebSocketService {
         this.isConnecting = false;
 
         // Resubscribe to all polls
+        if (this.subscribedToAll) {
+          this.send({ type: "SubscribeAll" });
+        }
         this.subscribers.forEach((_, pollId) => {
           this.sendSubscription(pollId);
         });
@@ -86,14 +90,26 @@ export class WebSocketService {
     }
   }
 
+  private send(message: object) {
+    if (this.ws?.readyState === WebSocket.OPEN) {
+      this.ws.send(JSON.stringify(message));
+    }
+  }
+
   private sendSubscription(pollId: string) {
+    this.send({
+      type: "Subscribe",
+      poll_id: pollId,
+    });
+  }
+
+  // Receive updates for every poll, not only subscribed ones
+  subscribeAll() {
+    this.subscribedToAll = true;
     if (this.ws?.readyState === WebSocket.OPEN) {
-      this.ws.send(
-        JSON.stringify({
-          type: "Subscribe",
-          poll_id: pollId,
-        })
-      );
+      this.send({ type: "SubscribeAll" });
+    } else {
+      this.connect();
     }
   }
 
@@ -116,6 +132,7 @@ export class WebSocketService {
       subscribers.delete(callback);
       if (subscribers.size === 0) {
         this.subscribers.delete(pollId);
+        this.send({ type: "Unsubscribe", poll_id: pollId });
       }
     }
   }
@@ -156,6 +173,12 @@ export class WebSocketService {
             callback(data.poll_id, data.poll);
           } else if (data.type === "PollDeleted" && data.poll_id) {
             callback(data.poll_id, null); // null indicates deletion
+          } else if (data.type === "Resync") {
+            // Updates were missed; this is the current state instead
+            data.polls.forEach((poll: any) => callback(poll.id, poll));
+            (data.deleted ?? []).forEach((pollId: string) => callback(pollId, null));
+          } else if (data.type === "Error") {
+            console.error(`WebSocket command failed (${data.code}): ${data.message}`);
           }
         } catch (error) {
           console.error("Error processing WebSocket message:", error);
@@ -168,6 +191,7 @@ export class WebSocketService {
     if (this.ws) {
       this.ws.close();
       this.subscribers.clear();
+      this.subscribedToAll = false;
     }
   }
 }
@@ -14,9 +14,9 @@ export default function ManagePollsPage() {
 
   useEffect(() => {
     const fetchUserPolls = async () => {
-      const response = await fetch(`/api/polls?creator=${username}`);
+      const response = await fetch("/api/me/polls?limit=100");
       const data = await response.json();
-      setPolls(data);
+      setPolls(data.items);
     };
 
     fetchUserPolls();
@@ -27,11 +27,11 @@ export default function PollsPage() {
 
     const fetchPolls = async () => {
       try {
-        const response = await fetch("/api/polls");
+        const response = await fetch("/api/polls?limit=100");
         if (!response.ok) throw new Error("Failed to fetch polls");
         const data = await response.json();
-        setPolls(data);
-        setFilteredPolls(data);
+        setPolls(data.items);
+        setFilteredPolls(data.items);
       } catch (err) {
         setError(err instanceof Error ? err.message : "Failed to load polls");
       } finally {
@@ -42,6 +42,7 @@ export default function PollsPage() {
     fetchPolls();
 
     // Subscribe to all poll updates
+    wsService.subscribeAll();
     wsService.onPollUpdate((pollId, updatedPoll) => {
       if (updatedPoll === null) {
         // Handle poll deletion